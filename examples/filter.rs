@@ -18,14 +18,14 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let rpc_endpoint = "";
     let provider = Arc::new(Provider::<Http>::try_from(rpc_endpoint).unwrap());
 
-    let mut dexes = vec![];
-
-    //Add UniswapV3
-    dexes.push(Dex::new(
-        H160::from_str("0x1F98431c8aD98523631AE4a59f267346ea31F984").unwrap(),
-        PoolVariant::UniswapV3,
-        12369621,
-    ));
+    let dexes = vec![
+        //Add UniswapV3
+        Dex::new(
+            H160::from_str("0x1F98431c8aD98523631AE4a59f267346ea31F984").unwrap(),
+            PoolVariant::UniswapV3,
+            12369621,
+        ),
+    ];
 
     //Sync pools
     let pools = sync::sync_pairs_with_throttle(dexes.clone(), provider.clone(), 10).await?;
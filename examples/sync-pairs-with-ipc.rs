@@ -18,28 +18,26 @@ async fn main() -> Result<(), Box<dyn Error>> {
             .interval(Duration::from_millis(2000)),
     );
 
-    let mut dexes = vec![];
-
-    //Add UniswapV2
-    dexes.push(Dex::new(
-        H160::from_str("0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f").unwrap(),
-        PoolVariant::UniswapV2,
-        2638438,
-    ));
-
-    //Add Sushiswap
-    dexes.push(Dex::new(
-        H160::from_str("0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac").unwrap(),
-        PoolVariant::UniswapV2,
-        10794229,
-    ));
-
-    //Add UniswapV3
-    dexes.push(Dex::new(
-        H160::from_str("0x1F98431c8aD98523631AE4a59f267346ea31F984").unwrap(),
-        PoolVariant::UniswapV3,
-        12369621,
-    ));
+    let dexes = vec![
+        //Add UniswapV2
+        Dex::new(
+            H160::from_str("0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f").unwrap(),
+            PoolVariant::UniswapV2,
+            2638438,
+        ),
+        //Add Sushiswap
+        Dex::new(
+            H160::from_str("0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac").unwrap(),
+            PoolVariant::UniswapV2,
+            10794229,
+        ),
+        //Add UniswapV3
+        Dex::new(
+            H160::from_str("0x1F98431c8aD98523631AE4a59f267346ea31F984").unwrap(),
+            PoolVariant::UniswapV3,
+            12369621,
+        ),
+    ];
 
     //Sync pairs
     sync::sync_pairs(dexes, provider).await?;
@@ -13,28 +13,38 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let rpc_endpoint = "";
     let provider = Arc::new(Provider::<Http>::try_from(rpc_endpoint).unwrap());
 
-    let mut dexes = vec![];
-
-    //Add UniswapV2
-    dexes.push(Dex::new(
-        H160::from_str("0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f").unwrap(),
-        PoolVariant::UniswapV2,
-        2638438,
-    ));
-
-    //Add Sushiswap
-    dexes.push(Dex::new(
-        H160::from_str("0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac").unwrap(),
-        PoolVariant::UniswapV2,
-        10794229,
-    ));
-
-    //Add UniswapV3
-    dexes.push(Dex::new(
-        H160::from_str("0x1F98431c8aD98523631AE4a59f267346ea31F984").unwrap(),
-        PoolVariant::UniswapV3,
-        12369621,
-    ));
+    let dexes = vec![
+        //Add UniswapV2
+        Dex::new(
+            H160::from_str("0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f").unwrap(),
+            PoolVariant::UniswapV2,
+            2638438,
+        ),
+        //Add Sushiswap
+        Dex::new(
+            H160::from_str("0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac").unwrap(),
+            PoolVariant::UniswapV2,
+            10794229,
+        ),
+        //Add UniswapV3
+        Dex::new(
+            H160::from_str("0x1F98431c8aD98523631AE4a59f267346ea31F984").unwrap(),
+            PoolVariant::UniswapV3,
+            12369621,
+        ),
+        //Add the Curve main registry
+        Dex::new(
+            H160::from_str("0x90E00ACe148ca3b23Ac1bC8C240C2a7Dd9c2d7f5").unwrap(),
+            PoolVariant::Curve,
+            12195750,
+        ),
+        //Add the Balancer V2 Vault
+        Dex::new(
+            H160::from_str("0xBA12222222228d8Ba445958a75a0704d566BF2C8").unwrap(),
+            PoolVariant::BalancerV2,
+            12272146,
+        ),
+    ];
 
     //Sync pairs
     sync::sync_pairs(dexes, provider).await?;
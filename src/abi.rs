@@ -4,6 +4,8 @@ abigen!(
     IUniswapV2Factory,
     r#"[
         function getPair(address tokenA, address tokenB) external view returns (address pair)
+        function allPairs(uint256) external view returns (address pair)
+        function allPairsLength() external view returns (uint256)
         event PairCreated(address indexed token0, address indexed token1, address pair, uint256)
     ]"#;
 
@@ -11,12 +13,16 @@ abigen!(
     r#"[
         function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast)
         function token0() external view returns (address)
+        function token1() external view returns (address)
+        function factory() external view returns (address)
     ]"#;
 
     IUniswapV3Factory,
     r#"[
         function getPool(address tokenA, address tokenB, uint24 fee) external view returns (address pool)
+        function feeAmountTickSpacing(uint24 fee) external view returns (int24)
         event PoolCreated(address indexed token0, address indexed token1, uint24 indexed fee, int24 tickSpacing, address pool)
+        event FeeAmountEnabled(uint24 indexed fee, int24 indexed tickSpacing)
     ]"#;
 
     IUniswapV3Pool,
@@ -24,14 +30,109 @@ abigen!(
         function token0() external view returns (address)
         function token1() external view returns (address)
         function liquidity() external view returns (uint128)
+        function fee() external view returns (uint24)
+        function factory() external view returns (address)
+        function tickSpacing() external view returns (int24)
         function slot0() external view returns (uint160, int24, uint16, uint16, uint16, uint8, bool)
         ]"#;
 
+    ISolidlyFactory,
+    r#"[
+        function getPair(address tokenA, address tokenB, bool stable) external view returns (address pair)
+        function getFee(bool stable) external view returns (uint256)
+        event PairCreated(address indexed token0, address indexed token1, bool stable, address pair, uint256)
+        event PoolCreated(address indexed token0, address indexed token1, bool indexed stable, address pool, uint256)
+    ]"#;
+
+    ISolidlyPoolFactory,
+    r#"[
+        function getFee(address pool, bool stable) external view returns (uint256)
+    ]"#;
+
+    ISolidlyPair,
+    r#"[
+        function stable() external view returns (bool)
+    ]"#;
+
     IErc20,
     r#"[
         function balanceOf(address account) external view returns (uint256)
         function decimals() external view returns (uint8)
     ]"#;
 
+    IAlgebraFactory,
+    r#"[
+        function poolByPair(address tokenA, address tokenB) external view returns (address pool)
+        event Pool(address indexed token0, address indexed token1, address pool)
+    ]"#;
+
+    IAlgebraPool,
+    r#"[
+        function globalState() external view returns (uint160 price, int24 tick, uint16 fee)
+        function liquidity() external view returns (uint128)
+        function tickSpacing() external view returns (int24)
+    ]"#;
+
+    IUniswapV4PoolManager,
+    r#"[
+        function extsload(bytes32 slot) external view returns (bytes32)
+        event Initialize(bytes32 indexed id, address indexed currency0, address indexed currency1, uint24 fee, int24 tickSpacing, address hooks, uint160 sqrtPriceX96, int24 tick)
+    ]"#;
+
+    ICurveRegistry,
+    r#"[
+        function pool_count() external view returns (uint256)
+        function pool_list(uint256 i) external view returns (address)
+        function find_pool_for_coins(address from, address to) external view returns (address)
+        function get_lp_token(address pool) external view returns (address)
+        event PoolAdded(address indexed pool, bytes rate_method_id)
+    ]"#;
+
+    ICurvePool,
+    r#"[
+        function coins(uint256 i) external view returns (address)
+        function balances(uint256 i) external view returns (uint256)
+        function underlying_coins(uint256 i) external view returns (address)
+        function A() external view returns (uint256)
+        function fee() external view returns (uint256)
+        function base_pool() external view returns (address)
+        function get_virtual_price() external view returns (uint256)
+    ]"#;
+
+    ICurvePoolLegacy,
+    r#"[
+        function coins(int128 i) external view returns (address)
+        function balances(int128 i) external view returns (uint256)
+        function underlying_coins(int128 i) external view returns (address)
+    ]"#;
+
+    IBalancerVault,
+    r#"[
+        function getPoolTokens(bytes32 poolId) external view returns (address[] tokens, uint256[] balances, uint256 lastChangeBlock)
+        function getPool(bytes32 poolId) external view returns (address, uint8)
+        event PoolRegistered(bytes32 indexed poolId, address indexed poolAddress, uint8 specialization)
+    ]"#;
+
+    IBalancerPool,
+    r#"[
+        function getPoolId() external view returns (bytes32)
+        function getVault() external view returns (address)
+        function getNormalizedWeights() external view returns (uint256[])
+        function getSwapFeePercentage() external view returns (uint256)
+        function getAmplificationParameter() external view returns (uint256 value, bool isUpdating, uint256 precision)
+    ]"#;
+
+    ICurveLendingToken,
+    r#"[
+        function exchangeRateStored() external view returns (uint256)
+        function getPricePerFullShare() external view returns (uint256)
+    ]"#;
+
+    IChainlinkAggregator,
+    r#"[
+        function decimals() external view returns (uint8)
+        function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound)
+    ]"#;
+
 
 );
@@ -0,0 +1,203 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ethers::{
+    providers::{JsonRpcClient, Middleware, Provider},
+    types::{BlockNumber, H160, I256, U256},
+};
+
+use crate::{abi, error::PairSyncError};
+
+//Latest round reported by a Chainlink aggregator
+#[derive(Debug, Clone, Copy)]
+pub struct RoundData {
+    pub round_id: u128,
+    pub answer: I256,
+    pub started_at: U256,
+    pub updated_at: U256,
+    pub answered_in_round: u128,
+}
+
+impl RoundData {
+    //Gets the answer as a price, scaled down by the aggregator's decimals. Returns None for non-positive or out of range answers.
+    pub fn get_price(&self, decimals: u8) -> Option<f64> {
+        if self.answer <= I256::zero() || self.answer.into_raw() > U256::from(u128::MAX) {
+            return None;
+        }
+
+        Some(self.answer.into_raw().as_u128() as f64 / 10f64.powf(decimals.into()))
+    }
+}
+
+//A Chainlink aggregator reporting the price of a token
+#[derive(Debug, Clone, Copy)]
+pub struct PriceFeed {
+    pub aggregator_address: H160,
+    pub decimals: u8,
+    //Maximum number of seconds between updates, answers older than this are stale
+    pub heartbeat: u64,
+}
+
+impl PriceFeed {
+    pub fn new(aggregator_address: H160, decimals: u8, heartbeat: u64) -> PriceFeed {
+        PriceFeed {
+            aggregator_address,
+            decimals,
+            heartbeat,
+        }
+    }
+
+    //Returns true if the round was not updated within the heartbeat of the timestamp or was carried over from a previous round
+    pub fn is_stale(&self, round_data: &RoundData, timestamp: U256) -> bool {
+        round_data.updated_at.is_zero()
+            || round_data.answered_in_round < round_data.round_id
+            || round_data.updated_at + U256::from(self.heartbeat) < timestamp
+    }
+}
+
+//Chainlink price feeds by token. Every feed in a registry should report prices in the same denomination,
+//ie. all in USD or all in ETH.
+#[derive(Debug, Clone, Default)]
+pub struct FeedRegistry {
+    pub feeds: HashMap<H160, PriceFeed>,
+}
+
+impl FeedRegistry {
+    pub fn new() -> FeedRegistry {
+        FeedRegistry::default()
+    }
+
+    pub fn with_feed(mut self, token: H160, price_feed: PriceFeed) -> FeedRegistry {
+        self.add_feed(token, price_feed);
+        self
+    }
+
+    pub fn add_feed(&mut self, token: H160, price_feed: PriceFeed) {
+        self.feeds.insert(token, price_feed);
+    }
+
+    pub fn get_feed(&self, token: H160) -> Option<&PriceFeed> {
+        self.feeds.get(&token)
+    }
+
+    //Gets the price of the token from its feed. Returns None if the token has no feed, the feed reverts, the answer is
+    //stale relative to the latest block or the answer is not positive, so the caller can fall back to another source.
+    pub async fn get_price<P: JsonRpcClient>(
+        &self,
+        token: H160,
+        provider: Arc<Provider<P>>,
+    ) -> Result<Option<f64>, PairSyncError<P>> {
+        let price_feed = match self.get_feed(token) {
+            Some(price_feed) => price_feed,
+            None => return Ok(None),
+        };
+
+        //Deprecated aggregators and aggregators without a first round revert on `latestRoundData`
+        let round_data =
+            match get_latest_round_data(price_feed.aggregator_address, provider.clone()).await {
+                Ok(round_data) => round_data,
+                Err(pair_sync_error) if pair_sync_error.is_revert() => return Ok(None),
+                Err(pair_sync_error) => return Err(pair_sync_error),
+            };
+
+        let timestamp = match provider.get_block(BlockNumber::Latest).await? {
+            Some(block) => block.timestamp,
+            None => return Ok(None),
+        };
+
+        if price_feed.is_stale(&round_data, timestamp) {
+            return Ok(None);
+        }
+
+        Ok(round_data.get_price(price_feed.decimals))
+    }
+}
+
+//Gets the latest round reported by a Chainlink aggregator
+pub async fn get_latest_round_data<P: JsonRpcClient>(
+    aggregator_address: H160,
+    provider: Arc<Provider<P>>,
+) -> Result<RoundData, PairSyncError<P>> {
+    let aggregator = abi::IChainlinkAggregator::new(aggregator_address, provider);
+
+    let (round_id, answer, started_at, updated_at, answered_in_round) =
+        aggregator.latest_round_data().call().await?;
+
+    Ok(RoundData {
+        round_id,
+        answer,
+        started_at,
+        updated_at,
+        answered_in_round,
+    })
+}
+
+//Gets the latest price reported by a Chainlink aggregator, reading the aggregator's decimals.
+//Fails if the answer was not updated within the heartbeat of the latest block, see `PriceFeed::is_stale`.
+pub async fn get_chainlink_price<P: JsonRpcClient>(
+    aggregator_address: H160,
+    heartbeat: u64,
+    provider: Arc<Provider<P>>,
+) -> Result<f64, PairSyncError<P>> {
+    let aggregator = abi::IChainlinkAggregator::new(aggregator_address, provider.clone());
+    let decimals = aggregator.decimals().call().await?;
+
+    let round_data = get_latest_round_data(aggregator_address, provider.clone()).await?;
+
+    let timestamp = match provider.get_block(BlockNumber::Latest).await? {
+        Some(block) => block.timestamp,
+        None => return Err(PairSyncError::StaleFeedAnswer(aggregator_address)),
+    };
+
+    if PriceFeed::new(aggregator_address, decimals, heartbeat).is_stale(&round_data, timestamp) {
+        return Err(PairSyncError::StaleFeedAnswer(aggregator_address));
+    }
+
+    round_data
+        .get_price(decimals)
+        .ok_or(PairSyncError::InvalidFeedAnswer(aggregator_address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_data(answer: i64) -> RoundData {
+        RoundData {
+            round_id: 10,
+            answer: I256::from(answer),
+            started_at: U256::from(1_700_000_000u64),
+            updated_at: U256::from(1_700_000_000u64),
+            answered_in_round: 10,
+        }
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let price_feed = PriceFeed::new(H160::zero(), 8, 3600);
+        let updated_at = 1_700_000_000u64;
+
+        assert!(!price_feed.is_stale(&round_data(1), U256::from(updated_at + 3600)));
+        assert!(price_feed.is_stale(&round_data(1), U256::from(updated_at + 3601)));
+
+        //Answers carried over from a previous round are stale
+        let mut carried_over = round_data(1);
+        carried_over.answered_in_round = 9;
+        assert!(price_feed.is_stale(&carried_over, U256::from(updated_at)));
+
+        //Rounds that were never updated are stale
+        let mut not_updated = round_data(1);
+        not_updated.updated_at = U256::zero();
+        assert!(price_feed.is_stale(&not_updated, U256::from(updated_at)));
+    }
+
+    #[test]
+    fn test_get_price() {
+        //An ETH / USD answer of 1850.12345678 with 8 decimals
+        assert_eq!(
+            round_data(185_012_345_678).get_price(8),
+            Some(1850.12345678)
+        );
+        assert_eq!(round_data(0).get_price(8), None);
+        assert_eq!(round_data(-1).get_price(8), None);
+    }
+}
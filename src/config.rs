@@ -0,0 +1,380 @@
+use std::{fs, path::Path, str::FromStr};
+
+use ethers::{
+    types::{H160, H256},
+    utils::id,
+};
+use serde::Deserialize;
+
+use crate::{
+    dex::{Dex, FeeModel, PoolDiscovery, MAX_FEE_BPS},
+    error::DexConfigError,
+    pool::PoolVariant,
+};
+
+//A list of dexes loaded from a TOML or JSON config file.
+//
+//```toml
+//chain_id = 1
+//
+//[[dexes]]
+//name = "uniswap_v2"
+//factory_address = "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"
+//pool_variant = "UniswapV2"
+//creation_block = 10000835
+//init_code_hash = "0x96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f"
+//fee_model = { type = "fixed", fee_bps = 30 }
+//```
+#[derive(Debug, Clone, Deserialize)]
+pub struct DexConfig {
+    //Chain id used for entries that do not set their own
+    pub chain_id: Option<u64>,
+    pub dexes: Vec<DexEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DexEntry {
+    pub name: Option<String>,
+    pub chain_id: Option<u64>,
+    pub factory_address: String,
+    pub pool_variant: String,
+    pub creation_block: u64,
+    pub fee_model: Option<FeeModelEntry>,
+    pub init_code_hash: Option<String>,
+    //Set to true to enumerate UniswapV2 pairs from the factory instead of reading logs
+    #[serde(default)]
+    pub factory_enumeration: bool,
+}
+
+//Fee model of a dex entry. Getters are given as function signatures, ie. "swapFee()".
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeeModelEntry {
+    Fixed {
+        fee_bps: u32,
+    },
+    PairGetter {
+        function: String,
+        fee_denominator: u32,
+    },
+    FactoryGetter {
+        function: String,
+        fee_denominator: u32,
+    },
+}
+
+impl DexConfig {
+    pub fn from_toml(config: &str) -> Result<DexConfig, DexConfigError> {
+        Ok(toml::from_str(config)?)
+    }
+
+    pub fn from_json(config: &str) -> Result<DexConfig, DexConfigError> {
+        Ok(serde_json::from_str(config)?)
+    }
+
+    //Reads a config file, parsing it as JSON if the extension is `.json` and as TOML otherwise
+    pub fn from_file<T: AsRef<Path>>(path: T) -> Result<DexConfig, DexConfigError> {
+        let config = fs::read_to_string(path.as_ref())?;
+
+        match path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("json") => DexConfig::from_json(&config),
+            _ => DexConfig::from_toml(&config),
+        }
+    }
+
+    //Validates every entry and converts the config into dexes
+    pub fn get_dexes(&self) -> Result<Vec<Dex>, DexConfigError> {
+        self.dexes.iter().map(|entry| entry.to_dex()).collect()
+    }
+
+    //Validates every entry and converts the entries for the chain into dexes.
+    //Entries without a chain id use the chain id of the config, and entries without either are included for every chain.
+    pub fn get_dexes_for_chain(&self, chain_id: u64) -> Result<Vec<Dex>, DexConfigError> {
+        let mut dexes = vec![];
+
+        for entry in &self.dexes {
+            let dex = entry.to_dex()?;
+
+            match entry.chain_id.or(self.chain_id) {
+                Some(entry_chain_id) if entry_chain_id != chain_id => {}
+                _ => dexes.push(dex),
+            }
+        }
+
+        Ok(dexes)
+    }
+}
+
+impl DexEntry {
+    //Name used to identify the entry in errors
+    fn label(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| self.factory_address.clone())
+    }
+
+    pub fn to_dex(&self) -> Result<Dex, DexConfigError> {
+        let factory_address = H160::from_str(&self.factory_address).map_err(|_| {
+            DexConfigError::InvalidAddress(self.label(), self.factory_address.clone())
+        })?;
+
+        let pool_variant = parse_pool_variant(&self.pool_variant).ok_or_else(|| {
+            DexConfigError::UnknownPoolVariant(self.label(), self.pool_variant.clone())
+        })?;
+
+        let mut dex = Dex::new(factory_address, pool_variant, self.creation_block);
+
+        if let Some(fee_model) = &self.fee_model {
+            dex = dex.with_fee_model(self.parse_fee_model(fee_model)?)?;
+        }
+
+        if let Some(init_code_hash) = &self.init_code_hash {
+            let init_code_hash = H256::from_str(init_code_hash).map_err(|_| {
+                DexConfigError::InvalidInitCodeHash(self.label(), init_code_hash.clone())
+            })?;
+
+            dex = dex.with_init_code_hash(init_code_hash);
+        }
+
+        if self.factory_enumeration {
+            dex = dex.with_pool_discovery(PoolDiscovery::FactoryEnumeration);
+        }
+
+        Ok(dex)
+    }
+
+    fn parse_fee_model(&self, fee_model: &FeeModelEntry) -> Result<FeeModel, DexConfigError> {
+        match fee_model {
+            FeeModelEntry::Fixed { fee_bps } => {
+                if *fee_bps > MAX_FEE_BPS {
+                    return Err(DexConfigError::InvalidFee(self.label(), *fee_bps));
+                }
+
+                Ok(FeeModel::Fixed(*fee_bps))
+            }
+            FeeModelEntry::PairGetter {
+                function,
+                fee_denominator,
+            } => Ok(FeeModel::PairGetter {
+                function_selector: self.parse_function_selector(function)?,
+                fee_denominator: self.parse_fee_denominator(*fee_denominator)?,
+            }),
+            FeeModelEntry::FactoryGetter {
+                function,
+                fee_denominator,
+            } => Ok(FeeModel::FactoryGetter {
+                function_selector: self.parse_function_selector(function)?,
+                fee_denominator: self.parse_fee_denominator(*fee_denominator)?,
+            }),
+        }
+    }
+
+    fn parse_fee_denominator(&self, fee_denominator: u32) -> Result<u32, DexConfigError> {
+        if fee_denominator == 0 {
+            return Err(DexConfigError::InvalidFeeDenominator(
+                self.label(),
+                fee_denominator,
+            ));
+        }
+
+        Ok(fee_denominator)
+    }
+
+    fn parse_function_selector(&self, function: &str) -> Result<[u8; 4], DexConfigError> {
+        //Only the name and argument types are hashed, so signatures must look like `swapFee()`
+        if !function.ends_with(')') || !function.contains('(') || function.contains(' ') {
+            return Err(DexConfigError::InvalidFunctionSignature(
+                self.label(),
+                function.to_string(),
+            ));
+        }
+
+        Ok(id(function))
+    }
+}
+
+//Parses a pool variant name, ignoring case and underscores so "UniswapV2" and "uniswap_v2" are both accepted
+fn parse_pool_variant(pool_variant: &str) -> Option<PoolVariant> {
+    match pool_variant.replace('_', "").to_lowercase().as_str() {
+        "uniswapv2" => Some(PoolVariant::UniswapV2),
+        "uniswapv3" => Some(PoolVariant::UniswapV3),
+        "uniswapv4" => Some(PoolVariant::UniswapV4),
+        "curve" => Some(PoolVariant::Curve),
+        "balancerv2" => Some(PoolVariant::BalancerV2),
+        "solidly" => Some(PoolVariant::Solidly),
+        "algebra" => Some(PoolVariant::Algebra),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+chain_id = 1
+
+[[dexes]]
+name = "uniswap_v2"
+factory_address = "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"
+pool_variant = "UniswapV2"
+creation_block = 10000835
+init_code_hash = "0x96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f"
+fee_model = { type = "fixed", fee_bps = 30 }
+
+[[dexes]]
+name = "pancakeswap_v2"
+chain_id = 56
+factory_address = "0xcA143Ce32Fe78f1f7019d7d551a6402fC5350c73"
+pool_variant = "uniswap_v2"
+creation_block = 6809737
+fee_model = { type = "pair_getter", function = "swapFee()", fee_denominator = 10000 }
+factory_enumeration = true
+"#;
+
+    fn parse_entry(entry: &str) -> Result<Vec<Dex>, DexConfigError> {
+        DexConfig::from_toml(&format!("[[dexes]]\n{}", entry))?.get_dexes()
+    }
+
+    #[test]
+    fn test_parse_toml_config() {
+        let dexes = DexConfig::from_toml(CONFIG).unwrap().get_dexes().unwrap();
+
+        assert_eq!(dexes.len(), 2);
+        assert_eq!(
+            dexes[0].factory_address,
+            H160::from_str("0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f").unwrap()
+        );
+        assert_eq!(dexes[0].pool_variant, PoolVariant::UniswapV2);
+        assert!(dexes[0].init_code_hash.is_some());
+        assert!(matches!(dexes[0].fee_model, FeeModel::Fixed(30)));
+        assert!(matches!(dexes[0].pool_discovery, PoolDiscovery::Logs));
+
+        assert!(matches!(
+            dexes[1].fee_model,
+            FeeModel::PairGetter {
+                function_selector,
+                fee_denominator: 10000,
+            } if function_selector == id("swapFee()")
+        ));
+        assert!(matches!(
+            dexes[1].pool_discovery,
+            PoolDiscovery::FactoryEnumeration
+        ));
+    }
+
+    #[test]
+    fn test_parse_json_config() {
+        let config = r#"{
+            "dexes": [{
+                "factory_address": "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+                "pool_variant": "UniswapV3",
+                "creation_block": 12369621,
+                "fee_model": { "type": "factory_getter", "function": "getPairFees(address)", "fee_denominator": 1000000 }
+            }]
+        }"#;
+
+        let dexes = DexConfig::from_json(config).unwrap().get_dexes().unwrap();
+
+        assert_eq!(dexes.len(), 1);
+        assert_eq!(dexes[0].pool_variant, PoolVariant::UniswapV3);
+        assert!(matches!(
+            dexes[0].fee_model,
+            FeeModel::FactoryGetter {
+                fee_denominator: 1000000,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_get_dexes_for_chain() {
+        let config = DexConfig::from_toml(CONFIG).unwrap();
+
+        assert_eq!(config.get_dexes_for_chain(1).unwrap().len(), 1);
+        assert_eq!(config.get_dexes_for_chain(56).unwrap().len(), 1);
+        assert!(config.get_dexes_for_chain(137).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reject_zero_fee_denominator() {
+        let result = parse_entry(
+            r#"name = "zero"
+factory_address = "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"
+pool_variant = "UniswapV2"
+creation_block = 0
+fee_model = { type = "factory_getter", function = "getPairFees(address)", fee_denominator = 0 }"#,
+        );
+
+        assert!(matches!(
+            result,
+            Err(DexConfigError::InvalidFeeDenominator(name, 0)) if name == "zero"
+        ));
+    }
+
+    #[test]
+    fn test_reject_fee_above_100_percent() {
+        let entry = |fee_bps: u32| {
+            parse_entry(&format!(
+                r#"name = "fee"
+factory_address = "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"
+pool_variant = "UniswapV2"
+creation_block = 0
+fee_model = {{ type = "fixed", fee_bps = {} }}"#,
+                fee_bps
+            ))
+        };
+
+        assert!(entry(10_000).is_ok());
+        assert!(matches!(
+            entry(500_000),
+            Err(DexConfigError::InvalidFee(name, 500_000)) if name == "fee"
+        ));
+    }
+
+    #[test]
+    fn test_reject_invalid_entries() {
+        assert!(matches!(
+            parse_entry(
+                r#"factory_address = "0x5C69"
+pool_variant = "UniswapV2"
+creation_block = 0"#
+            ),
+            Err(DexConfigError::InvalidAddress(_, _))
+        ));
+
+        assert!(matches!(
+            parse_entry(
+                r#"factory_address = "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"
+pool_variant = "UniswapV5"
+creation_block = 0"#
+            ),
+            Err(DexConfigError::UnknownPoolVariant(_, _))
+        ));
+
+        assert!(matches!(
+            parse_entry(
+                r#"factory_address = "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"
+pool_variant = "UniswapV2"
+creation_block = 0
+fee_model = { type = "pair_getter", function = "swapFee", fee_denominator = 10000 }"#
+            ),
+            Err(DexConfigError::InvalidFunctionSignature(_, _))
+        ));
+
+        assert!(matches!(
+            parse_entry(
+                r#"factory_address = "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"
+pool_variant = "UniswapV2"
+creation_block = 0
+init_code_hash = "0x96e8"
+"#
+            ),
+            Err(DexConfigError::InvalidInitCodeHash(_, _))
+        ));
+    }
+}
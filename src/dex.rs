@@ -1,22 +1,82 @@
-use std::sync::Arc;
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 use ethers::{
+    abi::{encode, Token},
     prelude::U256,
-    providers::{JsonRpcClient, Provider},
-    types::{Address, BlockNumber, Log, H160},
+    providers::{JsonRpcClient, Middleware, Provider},
+    types::{
+        Address, BlockNumber, Bytes, Filter, Log, TransactionRequest, ValueOrArray, H160, H256, U64,
+    },
+    utils::{get_create2_address_from_hash, keccak256},
 };
 
 use crate::{
     abi,
-    error::PairSyncError,
-    pool::{Pool, PoolVariant},
+    error::{DexConfigError, PairSyncError},
+    pool::{get_uniswap_v4_state, Pool, PoolVariant, UNISWAP_V4_DYNAMIC_FEE_FLAG},
+    throttle::RequestThrottle,
 };
 
-#[derive(Debug, Clone, Copy)]
+//UniswapV2 fees are stored on each pool in thousandths of a percent, so 300 is a 0.3% fee
+const UNISWAP_V2_FEE_DENOMINATOR: u64 = 100_000;
+//Fixed fees are in basis points, so 10,000 is a 100% fee
+pub(crate) const MAX_FEE_BPS: u32 = 10_000;
+
+//Fee tiers probed with `feeAmountTickSpacing` when FeeAmountEnabled logs can not be retrieved
+const DEFAULT_UNISWAP_V3_FEES: [u32; 4] = [100, 500, 3000, 10000];
+
+#[derive(Debug, Clone)]
 pub struct Dex {
     pub factory_address: H160,
     pub pool_variant: PoolVariant,
     pub creation_block: BlockNumber,
+    //How the swap fee of UniswapV2 pools from this dex is determined
+    pub fee_model: FeeModel,
+    //Keccak hash of the pool creation code, used to derive pool addresses with CREATE2
+    pub init_code_hash: Option<H256>,
+    //How pools from the factory are found when syncing
+    pub pool_discovery: PoolDiscovery,
+    //UniswapV3 fee tiers enabled on the factory, discovered on first use and shared between clones of the dex
+    fee_tiers: Arc<tokio::sync::OnceCell<Vec<FeeTier>>>,
+}
+
+//A UniswapV3 fee tier, with the fee in hundredths of a bip
+#[derive(Debug, Clone, Copy)]
+pub struct FeeTier {
+    pub fee: u32,
+    pub tick_spacing: i32,
+}
+
+//Strategy used to find every pool deployed by a factory
+#[derive(Debug, Clone, Copy)]
+pub enum PoolDiscovery {
+    //Pools are found from the factory's pool created logs
+    Logs,
+    //Pairs are enumerated with `allPairsLength()` and `allPairs(i)`, for providers that do not serve historical logs.
+    //Only supported for UniswapV2 dexes, other pool variants always use logs.
+    FactoryEnumeration,
+}
+
+//Swap fee model for UniswapV2 dexes. Other pool variants read their fees from the pool or factory.
+#[derive(Debug, Clone, Copy)]
+pub enum FeeModel {
+    //Every pair charges the same fee, in basis points
+    Fixed(u32),
+    //Each pair reports its own fee through a getter without arguments, ie. `swapFee()`.
+    //The fee is the returned value divided by `fee_denominator`.
+    PairGetter {
+        function_selector: [u8; 4],
+        fee_denominator: u32,
+    },
+    //The factory reports the fee of each pair through a getter that takes the pair address, ie. `getPairFees(address)`.
+    //The fee is the returned value divided by `fee_denominator`.
+    FactoryGetter {
+        function_selector: [u8; 4],
+        fee_denominator: u32,
+    },
 }
 
 impl Dex {
@@ -25,6 +85,411 @@ impl Dex {
             factory_address,
             pool_variant,
             creation_block: BlockNumber::Number(creation_block.into()),
+            //UniswapV2 and most forks charge 30 basis points
+            fee_model: FeeModel::Fixed(30),
+            init_code_hash: None,
+            pool_discovery: PoolDiscovery::Logs,
+            fee_tiers: Arc::new(tokio::sync::OnceCell::new()),
+        }
+    }
+
+    //Creates a new dex, finding the block the factory was deployed at from the provider.
+    //The provider must serve historical state, ie. an archive node.
+    pub async fn new_from_factory<P: JsonRpcClient>(
+        factory_address: H160,
+        pool_variant: PoolVariant,
+        provider: Arc<Provider<P>>,
+    ) -> Result<Dex, PairSyncError<P>> {
+        let creation_block = get_creation_block(factory_address, provider).await?;
+
+        Ok(Dex::new(factory_address, pool_variant, creation_block))
+    }
+
+    //Sets the fee model used for UniswapV2 pools from this dex.
+    //Fixed fees above 10,000 basis points and zero fee denominators are rejected.
+    pub fn with_fee_model(mut self, fee_model: FeeModel) -> Result<Dex, DexConfigError> {
+        match fee_model {
+            FeeModel::Fixed(fee_bps) if fee_bps > MAX_FEE_BPS => {
+                return Err(DexConfigError::InvalidFee(
+                    format!("{:?}", self.factory_address),
+                    fee_bps,
+                ))
+            }
+            FeeModel::PairGetter {
+                fee_denominator: 0, ..
+            }
+            | FeeModel::FactoryGetter {
+                fee_denominator: 0, ..
+            } => {
+                return Err(DexConfigError::InvalidFeeDenominator(
+                    format!("{:?}", self.factory_address),
+                    0,
+                ))
+            }
+            _ => {}
+        }
+
+        self.fee_model = fee_model;
+        Ok(self)
+    }
+
+    //Sets the strategy used to find pools from the factory when syncing
+    pub fn with_pool_discovery(mut self, pool_discovery: PoolDiscovery) -> Dex {
+        self.pool_discovery = pool_discovery;
+        self
+    }
+
+    //Sets the init code hash of pools deployed by the factory
+    pub fn with_init_code_hash(mut self, init_code_hash: H256) -> Dex {
+        self.init_code_hash = Some(init_code_hash);
+        self
+    }
+
+    //Gets the UniswapV3 fee tiers enabled on the factory. The tiers are discovered from the factory's
+    //FeeAmountEnabled logs the first time they are needed and cached on the dex afterwards.
+    pub async fn get_fee_tiers<P: JsonRpcClient>(
+        &self,
+        provider: Arc<Provider<P>>,
+    ) -> Result<Vec<FeeTier>, PairSyncError<P>> {
+        self.get_fee_tiers_with_request_throttle(
+            provider,
+            Arc::new(Mutex::new(RequestThrottle::new(0))),
+        )
+        .await
+    }
+
+    //Gets the UniswapV3 fee tiers enabled on the factory, see `get_fee_tiers`.
+    //Concurrent callers wait for the first discovery instead of scanning the logs again.
+    pub(crate) async fn get_fee_tiers_with_request_throttle<P: JsonRpcClient>(
+        &self,
+        provider: Arc<Provider<P>>,
+        request_throttle: Arc<Mutex<RequestThrottle>>,
+    ) -> Result<Vec<FeeTier>, PairSyncError<P>> {
+        let fee_tiers = self
+            .fee_tiers
+            .get_or_try_init(|| async {
+                let fee_tiers = self
+                    .get_fee_tiers_from_logs(provider.clone(), request_throttle.clone())
+                    .await?;
+
+                //No logs are found if the creation block is after the factory was deployed or the provider prunes old logs,
+                //so the common fee tiers are checked on the factory instead
+                if fee_tiers.is_empty() {
+                    return self
+                        .get_fee_tiers_from_factory(provider, request_throttle)
+                        .await;
+                }
+
+                Ok(fee_tiers)
+            })
+            .await?;
+
+        Ok(fee_tiers.clone())
+    }
+
+    async fn get_fee_tiers_from_logs<P: JsonRpcClient>(
+        &self,
+        provider: Arc<Provider<P>>,
+        request_throttle: Arc<Mutex<RequestThrottle>>,
+    ) -> Result<Vec<FeeTier>, PairSyncError<P>> {
+        let uniswap_v3_factory =
+            abi::IUniswapV3Factory::new(self.factory_address, provider.clone());
+
+        //Define the step for searching a range of blocks for fee amount enabled events
+        let step = 100000;
+        //Creation blocks given as a tag are scanned from genesis
+        let creation_block = self
+            .creation_block
+            .as_number()
+            .map(|creation_block| creation_block.as_u64())
+            .unwrap_or(0);
+
+        request_throttle.lock().unwrap().increment_or_sleep(1);
+        let current_block = provider.get_block_number().await?.as_u64();
+
+        let mut fee_tiers: Vec<FeeTier> = vec![];
+        for from_block in (creation_block..=current_block).step_by(step) {
+            //Log filters include both ends of the range
+            let to_block = (from_block + step as u64 - 1).min(current_block);
+
+            request_throttle.lock().unwrap().increment_or_sleep(1);
+            let logs = provider
+                .get_logs(
+                    &Filter::new()
+                        .topic0(ValueOrArray::Value(
+                            H256::from_str(
+                                "0xc66a3fdf07232cdd185febcc6579d408c241b47ae2f9907d84be655141eeaecc",
+                            )
+                            .unwrap(),
+                        ))
+                        .address(self.factory_address)
+                        .from_block(BlockNumber::Number(U64([from_block])))
+                        .to_block(BlockNumber::Number(U64([to_block]))),
+                )
+                .await?;
+
+            for log in logs {
+                let (fee, tick_spacing) = uniswap_v3_factory.decode_event::<(u32, i32)>(
+                    "FeeAmountEnabled",
+                    log.topics,
+                    log.data,
+                )?;
+
+                //Fee tiers can only be enabled once, but duplicates are skipped in case a log is returned twice
+                if !fee_tiers.iter().any(|fee_tier| fee_tier.fee == fee) {
+                    fee_tiers.push(FeeTier { fee, tick_spacing });
+                }
+            }
+        }
+
+        Ok(fee_tiers)
+    }
+
+    async fn get_fee_tiers_from_factory<P: JsonRpcClient>(
+        &self,
+        provider: Arc<Provider<P>>,
+        request_throttle: Arc<Mutex<RequestThrottle>>,
+    ) -> Result<Vec<FeeTier>, PairSyncError<P>> {
+        let uniswap_v3_factory = abi::IUniswapV3Factory::new(self.factory_address, provider);
+
+        let mut fee_tiers = vec![];
+        for fee in DEFAULT_UNISWAP_V3_FEES {
+            request_throttle.lock().unwrap().increment_or_sleep(1);
+
+            //A tick spacing of zero means the fee tier is not enabled
+            let tick_spacing = uniswap_v3_factory
+                .fee_amount_tick_spacing(fee)
+                .call()
+                .await?;
+
+            if tick_spacing != 0 {
+                fee_tiers.push(FeeTier { fee, tick_spacing });
+            }
+        }
+
+        Ok(fee_tiers)
+    }
+
+    //Gets the fee of a UniswapV2 pair from the dex fee model, in thousandths of a percent
+    pub async fn get_uniswap_v2_fee<P: JsonRpcClient>(
+        &self,
+        pair_address: H160,
+        provider: Arc<Provider<P>>,
+    ) -> Result<u32, PairSyncError<P>> {
+        let (to, data, fee_denominator) = match self.fee_model {
+            FeeModel::Fixed(fee_bps) if fee_bps <= MAX_FEE_BPS => return Ok(fee_bps * 10),
+            FeeModel::Fixed(_) => return Err(PairSyncError::InvalidPool(pair_address)),
+
+            FeeModel::PairGetter {
+                function_selector,
+                fee_denominator,
+            } => (pair_address, function_selector.to_vec(), fee_denominator),
+
+            FeeModel::FactoryGetter {
+                function_selector,
+                fee_denominator,
+            } => (
+                self.factory_address,
+                [
+                    function_selector.to_vec(),
+                    encode(&[Token::Address(pair_address)]),
+                ]
+                .concat(),
+                fee_denominator,
+            ),
+        };
+
+        let tx = TransactionRequest::new().to(to).data(data).into();
+        let result = provider.call(&tx, None).await?;
+
+        if result.len() < 32 {
+            return Err(PairSyncError::InvalidPool(pair_address));
+        }
+
+        let fee = U256::from_big_endian(&result[..32]);
+
+        //A zero denominator or a fee above 100% can not be converted to a pool fee
+        if fee_denominator == 0 || fee > U256::from(fee_denominator) {
+            return Err(PairSyncError::InvalidPool(pair_address));
+        }
+
+        let fee = fee * U256::from(UNISWAP_V2_FEE_DENOMINATOR) / U256::from(fee_denominator);
+
+        u32::try_from(fee).map_err(|_| PairSyncError::InvalidPool(pair_address))
+    }
+
+    //Computes the address of a UniswapV2 pair or UniswapV3 pool with CREATE2, without any calls to the provider.
+    //Returns None if the dex has no init code hash or the pool variant is not deployed with CREATE2 from the factory.
+    //The fee is only used for UniswapV3 pools.
+    pub fn compute_pool_address(&self, token_a: H160, token_b: H160, fee: u32) -> Option<H160> {
+        let init_code_hash = self.init_code_hash?;
+
+        let (token_0, token_1) = if token_a < token_b {
+            (token_a, token_b)
+        } else {
+            (token_b, token_a)
+        };
+
+        let salt = match self.pool_variant {
+            PoolVariant::UniswapV2 => keccak256([token_0.as_bytes(), token_1.as_bytes()].concat()),
+            PoolVariant::UniswapV3 => keccak256(encode(&[
+                Token::Address(token_0),
+                Token::Address(token_1),
+                Token::Uint(U256::from(fee)),
+            ])),
+            _ => return None,
+        };
+
+        Some(get_create2_address_from_hash(
+            self.factory_address,
+            salt,
+            init_code_hash.as_bytes().to_vec(),
+        ))
+    }
+
+    //Gets the address of a UniswapV2 pair or UniswapV3 pool, returning the zero address if the pool does not exist.
+    //When the address can be computed locally, the provider is only used to check that the pool has been deployed.
+    async fn get_pool_address<P: JsonRpcClient>(
+        &self,
+        token_a: H160,
+        token_b: H160,
+        fee: u32,
+        provider: Arc<Provider<P>>,
+    ) -> Result<H160, PairSyncError<P>> {
+        if let Some(pool_address) = self.compute_pool_address(token_a, token_b, fee) {
+            if provider.get_code(pool_address, None).await?.0.is_empty() {
+                return Ok(H160::zero());
+            }
+
+            return Ok(pool_address);
+        }
+
+        match self.pool_variant {
+            PoolVariant::UniswapV3 => {
+                Ok(abi::IUniswapV3Factory::new(self.factory_address, provider)
+                    .get_pool(token_a, token_b, fee)
+                    .call()
+                    .await?)
+            }
+            _ => Ok(abi::IUniswapV2Factory::new(self.factory_address, provider)
+                .get_pair(token_a, token_b)
+                .call()
+                .await?),
+        }
+    }
+
+    //Checks that the pool was deployed by this dex, so that contracts mimicking a pool interface are not trusted.
+    //The pool's own factory is checked where the interface exposes it, and the pool address is cross-checked
+    //against the factory, registry or vault lookup for the pool's tokens.
+    pub async fn verify_pool<P: 'static + JsonRpcClient>(
+        &self,
+        pool: &Pool,
+        provider: Arc<Provider<P>>,
+    ) -> Result<bool, PairSyncError<P>> {
+        if pool.pool_variant != self.pool_variant {
+            return Ok(false);
+        }
+
+        match self.pool_variant {
+            PoolVariant::UniswapV2 => {
+                //Pairs that do not implement factory() can not have been deployed by a UniswapV2 factory
+                match abi::IUniswapV2Pair::new(pool.address, provider.clone())
+                    .factory()
+                    .call()
+                    .await
+                {
+                    Ok(factory_address) if factory_address == self.factory_address => {}
+                    _ => return Ok(false),
+                }
+
+                let pair_address = revert_to_none(
+                    self.get_pool_address(pool.token_a, pool.token_b, 0, provider)
+                        .await,
+                )?;
+
+                Ok(pair_address == Some(pool.address))
+            }
+
+            PoolVariant::UniswapV3 => {
+                match abi::IUniswapV3Pool::new(pool.address, provider.clone())
+                    .factory()
+                    .call()
+                    .await
+                {
+                    Ok(factory_address) if factory_address == self.factory_address => {}
+                    _ => return Ok(false),
+                }
+
+                let pool_address = revert_to_none(
+                    self.get_pool_address(pool.token_a, pool.token_b, pool.fee, provider)
+                        .await,
+                )?;
+
+                Ok(pool_address == Some(pool.address))
+            }
+
+            PoolVariant::Solidly => {
+                let pair_address = revert_to_none(
+                    abi::ISolidlyFactory::new(self.factory_address, provider)
+                        .get_pair(pool.token_a, pool.token_b, pool.stable)
+                        .call()
+                        .await
+                        .map_err(PairSyncError::from),
+                )?;
+
+                Ok(pair_address == Some(pool.address))
+            }
+
+            PoolVariant::Algebra => {
+                let pool_address = revert_to_none(
+                    abi::IAlgebraFactory::new(self.factory_address, provider)
+                        .pool_by_pair(pool.token_a, pool.token_b)
+                        .call()
+                        .await
+                        .map_err(PairSyncError::from),
+                )?;
+
+                Ok(pool_address == Some(pool.address))
+            }
+
+            PoolVariant::Curve => {
+                //Pools that are not in the registry do not have an lp token registered
+                let lp_token = revert_to_none(
+                    abi::ICurveRegistry::new(self.factory_address, provider)
+                        .get_lp_token(pool.address)
+                        .call()
+                        .await
+                        .map_err(PairSyncError::from),
+                )?;
+
+                Ok(lp_token.is_some_and(|lp_token| !lp_token.is_zero()))
+            }
+
+            PoolVariant::BalancerV2 => {
+                //The vault reverts for pool ids that are not registered
+                let registered_pool = revert_to_none(
+                    abi::IBalancerVault::new(self.factory_address, provider)
+                        .get_pool(pool.pool_id.0)
+                        .call()
+                        .await
+                        .map_err(PairSyncError::from),
+                )?;
+
+                Ok(registered_pool.map(|(pool_address, _)| pool_address) == Some(pool.address))
+            }
+
+            PoolVariant::UniswapV4 => {
+                //UniswapV4 pools live in the PoolManager, so the pool id must be initialized there
+                if pool.address != self.factory_address {
+                    return Ok(false);
+                }
+
+                let pool_state = revert_to_none(
+                    get_uniswap_v4_state(self.factory_address, pool.pool_id, provider).await,
+                )?;
+
+                Ok(pool_state.is_some_and(|(sqrt_price, _, _)| !sqrt_price.is_zero()))
+            }
         }
     }
 
@@ -36,44 +501,217 @@ impl Dex {
         token_a: H160,
         token_b: H160,
         provider: Arc<Provider<P>>,
+    ) -> Result<(H160, u32), PairSyncError<P>> {
+        self.get_pool_with_best_liquidity_with_request_throttle(
+            token_a,
+            token_b,
+            provider,
+            Arc::new(Mutex::new(RequestThrottle::new(0))),
+        )
+        .await
+    }
+
+    //Gets the pool with the best liquidity, see `get_pool_with_best_liquidity`.
+    //The throttle is used when the dex's fee tiers are discovered.
+    pub(crate) async fn get_pool_with_best_liquidity_with_request_throttle<
+        P: 'static + JsonRpcClient,
+    >(
+        &self,
+        token_a: H160,
+        token_b: H160,
+        provider: Arc<Provider<P>>,
+        request_throttle: Arc<Mutex<RequestThrottle>>,
     ) -> Result<(H160, u32), PairSyncError<P>> {
         match self.pool_variant {
             PoolVariant::UniswapV2 => {
-                let uniswap_v2_factory =
-                    abi::IUniswapV2Factory::new(self.factory_address, provider);
+                let pair_address = self
+                    .get_pool_address(token_a, token_b, 0, provider.clone())
+                    .await?;
+
+                if pair_address.is_zero() {
+                    return Ok((pair_address, 0));
+                }
 
                 Ok((
-                    uniswap_v2_factory.get_pair(token_a, token_b).call().await?,
-                    300,
+                    pair_address,
+                    self.get_uniswap_v2_fee(pair_address, provider).await?,
                 ))
             }
 
             PoolVariant::UniswapV3 => {
-                let uniswap_v3_factory =
-                    abi::IUniswapV3Factory::new(self.factory_address, provider.clone());
-
                 let mut best_liquidity = 0;
                 let mut best_pool_address = H160::zero();
-                let mut best_fee = 100;
+                let mut best_fee = 0;
 
-                for fee in [100, 300, 500, 1000] {
-                    let pool_address = uniswap_v3_factory
-                        .get_pool(token_a, token_b, fee)
-                        .call()
+                for fee_tier in self
+                    .get_fee_tiers_with_request_throttle(provider.clone(), request_throttle)
+                    .await?
+                {
+                    let pool_address = self
+                        .get_pool_address(token_a, token_b, fee_tier.fee, provider.clone())
                         .await?;
 
+                    if pool_address.is_zero() {
+                        continue;
+                    }
+
                     let uniswap_v3_pool = abi::IUniswapV3Pool::new(pool_address, provider.clone());
 
                     let liquidity = uniswap_v3_pool.liquidity().call().await?;
                     if best_liquidity < liquidity {
                         best_liquidity = liquidity;
                         best_pool_address = pool_address;
-                        best_fee = fee;
+                        best_fee = fee_tier.fee;
                     }
                 }
 
                 Ok((best_pool_address, best_fee))
             }
+
+            PoolVariant::Curve => {
+                let curve_registry =
+                    abi::ICurveRegistry::new(self.factory_address, provider.clone());
+
+                let pool_address = curve_registry
+                    .find_pool_for_coins(token_a, token_b)
+                    .call()
+                    .await?;
+
+                if pool_address.is_zero() {
+                    return Ok((pool_address, 0));
+                }
+
+                let fee = abi::ICurvePool::new(pool_address, provider)
+                    .fee()
+                    .call()
+                    .await?;
+
+                let fee =
+                    u32::try_from(fee).map_err(|_| PairSyncError::InvalidPool(pool_address))?;
+
+                Ok((pool_address, fee))
+            }
+
+            //The Balancer Vault does not index pools by token pair
+            PoolVariant::BalancerV2 => Ok((H160::zero(), 0)),
+
+            //Solidly factories have a stable and a volatile pair for each token pair
+            PoolVariant::Solidly => {
+                let solidly_factory =
+                    abi::ISolidlyFactory::new(self.factory_address, provider.clone());
+
+                let mut best_reserve = 0;
+                let mut best_pool_address = H160::zero();
+                let mut best_stable = false;
+
+                for stable in [false, true] {
+                    let pool_address = solidly_factory
+                        .get_pair(token_a, token_b, stable)
+                        .call()
+                        .await?;
+
+                    if pool_address.is_zero() {
+                        continue;
+                    }
+
+                    //Both pairs hold the same token0, so reserve0 can be compared directly
+                    let (reserve_0, _) = self
+                        .pool_variant
+                        .get_reserves(pool_address, provider.clone())
+                        .await?;
+
+                    if best_reserve < reserve_0 {
+                        best_reserve = reserve_0;
+                        best_pool_address = pool_address;
+                        best_stable = stable;
+                    }
+                }
+
+                if best_pool_address.is_zero() {
+                    return Ok((best_pool_address, 0));
+                }
+
+                let fee = self
+                    .get_solidly_fee(best_pool_address, best_stable, provider)
+                    .await?;
+
+                Ok((best_pool_address, fee))
+            }
+
+            //UniswapV4 pools are identified by pool id rather than by address
+            PoolVariant::UniswapV4 => Ok((H160::zero(), 0)),
+
+            //Algebra factories have a single pool for each token pair with a dynamic fee
+            PoolVariant::Algebra => {
+                let algebra_factory =
+                    abi::IAlgebraFactory::new(self.factory_address, provider.clone());
+
+                let pool_address = algebra_factory
+                    .pool_by_pair(token_a, token_b)
+                    .call()
+                    .await?;
+
+                if pool_address.is_zero() {
+                    return Ok((pool_address, 0));
+                }
+
+                let (_, _, fee) = abi::IAlgebraPool::new(pool_address, provider)
+                    .global_state()
+                    .call()
+                    .await?;
+
+                Ok((pool_address, fee.into()))
+            }
+        }
+    }
+
+    //Gets the fee of a Solidly pair in basis points. Newer factories set fees per pool,
+    //older factories only have a fee for stable pairs and a fee for volatile pairs.
+    pub(crate) async fn get_solidly_fee<P: JsonRpcClient>(
+        &self,
+        pool_address: H160,
+        stable: bool,
+        provider: Arc<Provider<P>>,
+    ) -> Result<u32, PairSyncError<P>> {
+        let fee = match abi::ISolidlyPoolFactory::new(self.factory_address, provider.clone())
+            .get_fee(pool_address, stable)
+            .call()
+            .await
+        {
+            Ok(fee) => fee,
+            Err(_) => {
+                abi::ISolidlyFactory::new(self.factory_address, provider)
+                    .get_fee(stable)
+                    .call()
+                    .await?
+            }
+        };
+
+        u32::try_from(fee).map_err(|_| PairSyncError::InvalidPool(pool_address))
+    }
+
+    //Creates a UniswapV2 pool from the pair address and tokens, with the fee from the dex fee model
+    pub(crate) fn new_uniswap_v2_pool(&self, address: H160, token_a: H160, token_b: H160) -> Pool {
+        Pool {
+            pool_variant: PoolVariant::UniswapV2,
+            address,
+            token_a,
+            token_b,
+            //Initialize the following variables as zero values
+            //They will be populated when getting pair reserves
+            token_a_decimals: 0,
+            token_b_decimals: 0,
+            a_to_b: false,
+            reserve_0: 0,
+            reserve_1: 0,
+            //Fees read from a getter are populated when getting pair reserves
+            fee: match self.fee_model {
+                //Fees above 100% fail in swap simulations instead of overflowing here
+                FeeModel::Fixed(fee_bps) => fee_bps.saturating_mul(10),
+                _ => 0,
+            },
+            factory_address: self.factory_address,
+            ..Pool::empty_pool(PoolVariant::UniswapV2)
         }
     }
 
@@ -82,7 +720,11 @@ impl Dex {
         log: Log,
         provider: Arc<Provider<P>>,
     ) -> Result<Pool, PairSyncError<P>> {
-        match self.pool_variant {
+        //Keep the creation metadata before the log topics and data are consumed by decoding
+        let creation_block = log.block_number.unwrap_or_default().as_u64();
+        let creation_tx_hash = log.transaction_hash.unwrap_or_default();
+
+        let mut pool = match self.pool_variant {
             PoolVariant::UniswapV2 => {
                 let uniswap_v2_factory =
                     abi::IUniswapV2Factory::new(self.factory_address, provider);
@@ -94,33 +736,20 @@ impl Dex {
                         log.data,
                     )?;
 
-                Ok(Pool {
-                    pool_variant: PoolVariant::UniswapV2,
-                    address,
-                    token_a,
-                    token_b,
-                    //Initialize the following variables as zero values
-                    //They will be populated when getting pair reserves
-                    token_a_decimals: 0,
-                    token_b_decimals: 0,
-                    a_to_b: false,
-                    reserve_0: 0,
-                    reserve_1: 0,
-                    fee: 300,
-                })
+                self.new_uniswap_v2_pool(address, token_a, token_b)
             }
             PoolVariant::UniswapV3 => {
                 let uniswap_v3_factory =
                     abi::IUniswapV3Factory::new(self.factory_address, provider);
 
-                let (token_a, token_b, fee, _, address) =
-                    uniswap_v3_factory.decode_event::<(Address, Address, u32, u128, Address)>(
+                let (token_a, token_b, fee, tick_spacing, address) = uniswap_v3_factory
+                    .decode_event::<(Address, Address, u32, i32, Address)>(
                         "PoolCreated",
                         log.topics,
                         log.data,
                     )?;
 
-                Ok(Pool {
+                Pool {
                     pool_variant: PoolVariant::UniswapV3,
                     address,
                     token_a,
@@ -133,8 +762,226 @@ impl Dex {
                     reserve_0: 0,
                     reserve_1: 0,
                     fee,
-                })
+                    tick_spacing,
+                    ..Pool::empty_pool(PoolVariant::UniswapV3)
+                }
+            }
+            PoolVariant::Curve => {
+                let curve_registry = abi::ICurveRegistry::new(self.factory_address, provider);
+
+                let (address, _) = curve_registry.decode_event::<(Address, Bytes)>(
+                    "PoolAdded",
+                    log.topics,
+                    log.data,
+                )?;
+
+                //The coins, balances and fee are populated when getting pool reserves
+                Pool::new_curve_pool(address)
+            }
+            PoolVariant::BalancerV2 => {
+                let balancer_vault = abi::IBalancerVault::new(self.factory_address, provider);
+
+                let (pool_id, address, _) = balancer_vault
+                    .decode_event::<([u8; 32], Address, u8)>(
+                        "PoolRegistered",
+                        log.topics,
+                        log.data,
+                    )?;
+
+                //The tokens, balances, weights and fee are populated when getting pool reserves
+                Pool {
+                    address,
+                    pool_id: H256::from(pool_id),
+                    ..Pool::empty_pool(PoolVariant::BalancerV2)
+                }
             }
+            PoolVariant::Solidly => {
+                let solidly_factory = abi::ISolidlyFactory::new(self.factory_address, provider);
+
+                //Aerodrome and Velodrome V2 factories emit PoolCreated with the same fields as PairCreated
+                let event_name = if log.topics.first()
+                    == Some(&PoolVariant::Solidly.pool_created_event_signature())
+                {
+                    "PairCreated"
+                } else {
+                    "PoolCreated"
+                };
+
+                let (token_a, token_b, stable, address, _) =
+                    solidly_factory.decode_event::<(Address, Address, bool, Address, U256)>(
+                        event_name, log.topics, log.data,
+                    )?;
+
+                Pool {
+                    address,
+                    token_a,
+                    token_b,
+                    stable,
+                    //The fee is read from the factory when getting pair reserves
+                    ..Pool::empty_pool(PoolVariant::Solidly)
+                }
+            }
+            PoolVariant::UniswapV4 => {
+                let pool_manager = abi::IUniswapV4PoolManager::new(self.factory_address, provider);
+
+                let (pool_id, token_a, token_b, fee, tick_spacing, hooks, _, _) = pool_manager
+                    .decode_event::<([u8; 32], Address, Address, u32, i32, Address, U256, i32)>(
+                        "Initialize",
+                        log.topics,
+                        log.data,
+                    )?;
+
+                Pool {
+                    //UniswapV4 pools are held by the PoolManager and identified by their pool id
+                    address: self.factory_address,
+                    pool_id: H256::from(pool_id),
+                    token_a,
+                    token_b,
+                    //token_a is currency0
+                    a_to_b: true,
+                    fee,
+                    tick_spacing,
+                    hooks,
+                    dynamic_fee: fee == UNISWAP_V4_DYNAMIC_FEE_FLAG,
+                    ..Pool::empty_pool(PoolVariant::UniswapV4)
+                }
+            }
+            PoolVariant::Algebra => {
+                let algebra_factory = abi::IAlgebraFactory::new(self.factory_address, provider);
+
+                let (token_a, token_b, address) = algebra_factory
+                    .decode_event::<(Address, Address, Address)>("Pool", log.topics, log.data)?;
+
+                Pool {
+                    address,
+                    token_a,
+                    token_b,
+                    //The dynamic fee is populated when getting pool reserves
+                    ..Pool::empty_pool(PoolVariant::Algebra)
+                }
+            }
+        };
+
+        pool.creation_block = creation_block;
+        pool.creation_tx_hash = creation_tx_hash;
+        pool.factory_address = self.factory_address;
+
+        Ok(pool)
+    }
+}
+
+//Gets the block a contract was deployed at by binary searching for the first block where the address has code
+pub async fn get_creation_block<P: JsonRpcClient>(
+    address: H160,
+    provider: Arc<Provider<P>>,
+) -> Result<u64, PairSyncError<P>> {
+    let current_block = provider.get_block_number().await?.as_u64();
+
+    if provider.get_code(address, None).await?.0.is_empty() {
+        return Err(PairSyncError::ContractNotDeployed(address));
+    }
+
+    let mut low = 0;
+    let mut high = current_block;
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+
+        let code = provider
+            .get_code(address, Some(BlockNumber::Number(mid.into()).into()))
+            .await?;
+
+        if code.0.is_empty() {
+            low = mid + 1;
+        } else {
+            high = mid;
         }
     }
+
+    Ok(low)
+}
+
+//Maps a reverted call to None, so a lookup on a contract that does not recognize the pool is not treated as a request failure
+fn revert_to_none<T, P: JsonRpcClient>(
+    result: Result<T, PairSyncError<P>>,
+) -> Result<Option<T>, PairSyncError<P>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(pair_sync_error) if pair_sync_error.is_revert() => Ok(None),
+        Err(pair_sync_error) => Err(pair_sync_error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn address(address: &str) -> H160 {
+        H160::from_str(address).unwrap()
+    }
+
+    #[test]
+    fn test_compute_pool_address() {
+        let weth = address("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let usdc = address("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+
+        let uniswap_v2 = Dex::new(
+            address("0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"),
+            PoolVariant::UniswapV2,
+            10000835,
+        )
+        .with_init_code_hash(
+            H256::from_str("0x96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f")
+                .unwrap(),
+        );
+
+        //The token order does not change the address
+        assert_eq!(
+            uniswap_v2.compute_pool_address(weth, usdc, 0),
+            Some(address("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"))
+        );
+        assert_eq!(
+            uniswap_v2.compute_pool_address(usdc, weth, 0),
+            Some(address("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"))
+        );
+
+        let uniswap_v3 = Dex::new(
+            address("0x1F98431c8aD98523631AE4a59f267346ea31F984"),
+            PoolVariant::UniswapV3,
+            12369621,
+        )
+        .with_init_code_hash(
+            H256::from_str("0xe34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54")
+                .unwrap(),
+        );
+
+        assert_eq!(
+            uniswap_v3.compute_pool_address(weth, usdc, 500),
+            Some(address("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"))
+        );
+        assert_eq!(
+            uniswap_v3.compute_pool_address(weth, usdc, 3000),
+            Some(address("0x8ad599c3A0ff1De082011EFDDc58f1908eb6e6D8"))
+        );
+    }
+
+    #[test]
+    fn test_compute_pool_address_unsupported() {
+        let weth = address("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let usdc = address("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+
+        //Without an init code hash the address can not be computed
+        let dex = Dex::new(
+            address("0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"),
+            PoolVariant::UniswapV2,
+            0,
+        );
+        assert!(dex.compute_pool_address(weth, usdc, 0).is_none());
+
+        //Curve pools are not deployed with CREATE2 from the registry
+        let dex = Dex::new(H160::zero(), PoolVariant::Curve, 0).with_init_code_hash(H256::zero());
+        assert!(dex.compute_pool_address(weth, usdc, 0).is_none());
+    }
 }
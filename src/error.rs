@@ -1,9 +1,11 @@
 use ethers::prelude::{AbiError, ContractError};
 use ethers::providers::{JsonRpcClient, Provider, ProviderError};
-use ethers::types::H160;
+use ethers::types::{H160, U256};
 use thiserror::Error;
 use tokio::task::JoinError;
 
+use crate::usd::PriceDisagreement;
+
 #[derive(Error, Debug)]
 pub enum PairSyncError<P>
 where
@@ -19,4 +21,95 @@ where
     JoinError(#[from] JoinError),
     #[error("Pair for token_a/token_b does not exist in provided dexes")]
     PairDoesNotExistInDexes(H160, H160),
+    #[error("Address is not a valid pool for the pool variant")]
+    InvalidPool(H160),
+    #[error("Address does not implement the interface of a known AMM")]
+    UnknownPoolVariant(H160),
+    #[error("No contract is deployed at the address")]
+    ContractNotDeployed(H160),
+    #[error("Chain is not supported by the dex registry")]
+    UnsupportedChain(U256),
+    #[error("Provider chain id does not match the expected chain")]
+    ChainIdMismatch(u64, U256),
+    #[error("Unknown dexes {1:?} for chain id {0}")]
+    UnknownDexes(u64, Vec<String>),
+    #[error("Transfer simulation returned an unexpected result")]
+    InvalidSimulationResult(H160),
+    #[error("Reference pool does not contain the token being converted")]
+    InvalidReferencePool(H160, H160),
+    #[error("None of the reference pools could price the token")]
+    NoPriceSources(H160),
+    #[error("Price sources disagree beyond the tolerance")]
+    PriceSourcesDisagree(H160, Vec<PriceDisagreement>),
+    #[error("Token balances of the pool are not held separately from other pools")]
+    UnknownPoolBalances(H160),
+    #[error("Chainlink aggregator returned a non-positive answer")]
+    InvalidFeedAnswer(H160),
+    #[error("Chainlink aggregator answer was not updated within the heartbeat")]
+    StaleFeedAnswer(H160),
+    #[error("Swap simulation error")]
+    SwapSimulationError(#[from] SwapSimulationError),
+}
+
+impl<P: JsonRpcClient> PairSyncError<P> {
+    //Returns true if the error is a contract call that reverted or returned data that could not be decoded,
+    //rather than a failure to reach the node
+    pub(crate) fn is_revert(&self) -> bool {
+        match self {
+            PairSyncError::ContractError(ContractError::MiddlewareError(provider_error))
+            | PairSyncError::ContractError(ContractError::ProviderError(provider_error))
+            | PairSyncError::ProviderError(provider_error) => {
+                provider_error.to_string().contains("revert")
+            }
+            PairSyncError::ContractError(ContractError::DecodingError(_))
+            | PairSyncError::ContractError(ContractError::DetokenizationError(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SwapSimulationError {
+    #[error("Token is not held by the pool")]
+    TokenNotInPool(H160),
+    #[error("Pool has insufficient liquidity for the swap")]
+    InsufficientLiquidity,
+    #[error("Invariant calculation did not converge")]
+    InvariantDidNotConverge,
+    #[error("Pool fee is above the fee denominator")]
+    InvalidFee(u32),
+    #[error("Swap moves the price out of the current tick range")]
+    TickRangeExceeded,
+}
+
+#[derive(Error, Debug)]
+pub enum DexConfigError {
+    #[error("Could not read dex config file")]
+    IoError(#[from] std::io::Error),
+    #[error("Could not parse TOML dex config")]
+    TomlError(#[from] toml::de::Error),
+    #[error("Could not parse JSON dex config")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Invalid factory address `{1}` for dex `{0}`")]
+    InvalidAddress(String, String),
+    #[error("Unknown pool variant `{1}` for dex `{0}`")]
+    UnknownPoolVariant(String, String),
+    #[error("Invalid init code hash `{1}` for dex `{0}`")]
+    InvalidInitCodeHash(String, String),
+    #[error("Invalid fee getter function signature `{1}` for dex `{0}`")]
+    InvalidFunctionSignature(String, String),
+    #[error("Invalid fee denominator `{1}` for dex `{0}`")]
+    InvalidFeeDenominator(String, u32),
+    #[error("Fee of `{1}` basis points for dex `{0}` is above 100%")]
+    InvalidFee(String, u32),
+}
+
+#[derive(Error, Debug)]
+pub enum TokenListError {
+    #[error("Could not read token list file")]
+    IoError(#[from] std::io::Error),
+    #[error("Could not parse JSON token list")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Invalid token address `{0}`")]
+    InvalidAddress(String),
 }
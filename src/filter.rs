@@ -1,21 +1,33 @@
 use crate::dex::Dex;
 use crate::error::PairSyncError;
-use crate::pool::{Pool, PoolVariant};
+use crate::pipeline::FilterContext;
+use crate::pool::{is_native_token, Pool, PoolKey};
+use crate::report::{ExclusionReason, FilterReport};
+use crate::simulation::{
+    find_simulator_balance_slot, simulate_pool_transfer_tax, simulate_round_trip_with_balance_slot,
+    TransferTax,
+};
 use crate::throttle::RequestThrottle;
+use crate::tvl::{get_token_values, PoolTvl, QuoteToken};
 use ethers::providers::{JsonRpcClient, Provider};
 use ethers::types::H160;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use std::collections::HashMap;
-use std::sync::Mutex;
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
 
-//Filters out pools where the blacklisted address is the token_a address or token_b address
+//Filters out pools where the blacklisted address is the token_a address, token_b address or one of the pool's tokens
 pub fn filter_blacklisted_tokens(pools: Vec<Pool>, blacklisted_addresses: Vec<H160>) -> Vec<Pool> {
     let mut filtered_pools = vec![];
     let blacklist: HashSet<H160> = blacklisted_addresses.into_iter().collect();
 
     for pool in pools {
-        if !blacklist.contains(&pool.token_a) || !blacklist.contains(&pool.token_b) {
+        if !blacklist.contains(&pool.token_a)
+            && !blacklist.contains(&pool.token_b)
+            && !pool.tokens.iter().any(|token| blacklist.contains(token))
+        {
             filtered_pools.push(pool);
         }
     }
@@ -23,13 +35,16 @@ pub fn filter_blacklisted_tokens(pools: Vec<Pool>, blacklisted_addresses: Vec<H1
     filtered_pools
 }
 
-//Filters out pools where the blacklisted address is the pair address
-pub fn filter_blacklisted_pools(pools: Vec<Pool>, blacklisted_addresses: Vec<H160>) -> Vec<Pool> {
+//Filters out blacklisted pools, given as pair addresses or as pool ids for UniswapV4 pools
+pub fn filter_blacklisted_pools<K: Into<PoolKey>>(
+    pools: Vec<Pool>,
+    blacklisted_pools: Vec<K>,
+) -> Vec<Pool> {
     let mut filtered_pools = vec![];
-    let blacklist: HashSet<H160> = blacklisted_addresses.into_iter().collect();
+    let blacklist: HashSet<PoolKey> = blacklisted_pools.into_iter().map(Into::into).collect();
 
     for pool in pools {
-        if !blacklist.contains(&pool.address) {
+        if !blacklist.contains(&pool.get_pool_key()) {
             filtered_pools.push(pool);
         }
     }
@@ -37,7 +52,8 @@ pub fn filter_blacklisted_pools(pools: Vec<Pool>, blacklisted_addresses: Vec<H16
     filtered_pools
 }
 
-//Filters out pools where the blacklisted address is the pair address, token_a address or token_b address
+//Filters out pools where the blacklisted address is the pair address, token_a address, token_b address or one of the
+//pool's tokens
 pub fn filter_blacklisted_addresses(
     pools: Vec<Pool>,
     blacklisted_addresses: Vec<H160>,
@@ -47,8 +63,9 @@ pub fn filter_blacklisted_addresses(
 
     for pool in pools {
         if !blacklist.contains(&pool.address)
-            || !blacklist.contains(&pool.token_a)
-            || !blacklist.contains(&pool.token_b)
+            && !blacklist.contains(&pool.token_a)
+            && !blacklist.contains(&pool.token_b)
+            && !pool.tokens.iter().any(|token| blacklist.contains(token))
         {
             filtered_pools.push(pool);
         }
@@ -57,167 +74,146 @@ pub fn filter_blacklisted_addresses(
     filtered_pools
 }
 
-//Filter that removes pools with that contain less than a specified usd value
-pub async fn filter_pools_below_usd_threshold<P: 'static + JsonRpcClient>(
+//Tokens of a pool that must be allowlisted for the pool to be kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllowlistMode {
+    //Keep pools where at least one token is allowlisted, ie. pools paired with a trusted base token
+    AnyToken,
+    //Keep pools where every token is allowlisted
+    AllTokens,
+}
+
+//Filters out pools whose tokens are not allowlisted, requiring one or all of the pool's tokens to be in the allowlist
+pub fn filter_pools_outside_allowlist(
+    pools: Vec<Pool>,
+    allowlisted_addresses: Vec<H160>,
+    allowlist_mode: AllowlistMode,
+) -> Vec<Pool> {
+    let mut filtered_pools = vec![];
+    let allowlist: HashSet<H160> = allowlisted_addresses.into_iter().collect();
+
+    for pool in pools {
+        if get_disallowed_token(&pool, &allowlist, allowlist_mode).is_none() {
+            filtered_pools.push(pool);
+        }
+    }
+
+    filtered_pools
+}
+
+//Gets the token that places the pool outside the allowlist, or None if the pool is kept.
+//In `AllTokens` mode this is the first token that is not allowlisted, and in `AnyToken` mode pools without
+//an allowlisted token return their first token. The pool's tokens are used when set, ie. for Curve and Balancer
+//pools, so unset token_a and token_b addresses are not checked.
+pub(crate) fn get_disallowed_token(
+    pool: &Pool,
+    allowlist: &HashSet<H160>,
+    allowlist_mode: AllowlistMode,
+) -> Option<H160> {
+    let pool_tokens = if pool.tokens.is_empty() {
+        vec![pool.token_a, pool.token_b]
+    } else {
+        pool.tokens.clone()
+    };
+
+    match allowlist_mode {
+        AllowlistMode::AnyToken => {
+            if pool_tokens.iter().any(|token| allowlist.contains(token)) {
+                None
+            } else {
+                pool_tokens.first().copied()
+            }
+        }
+        AllowlistMode::AllTokens => pool_tokens
+            .into_iter()
+            .find(|token| !allowlist.contains(token)),
+    }
+}
+
+//Filter that removes pools that were not deployed by any of the trusted dexes.
+//Each pool is verified against the dexes with the same pool variant, see `Dex::verify_pool`.
+pub async fn filter_unverified_pools<P: 'static + JsonRpcClient>(
     pools: Vec<Pool>,
     dexes: Vec<Dex>,
-    usd_weth_pool: Pool,
-    weth_address: H160,
-    usd_threshold: f64,
     provider: Arc<Provider<P>>,
 ) -> Result<Vec<Pool>, PairSyncError<P>> {
-    filter_pools_below_usd_threshold_with_throttle(
+    filter_unverified_pools_with_throttle(pools, dexes, provider, 0).await
+}
+
+//Filter that removes pools that were not deployed by any of the trusted dexes.
+//Each pool is verified against the dexes with the same pool variant, see `Dex::verify_pool`.
+pub async fn filter_unverified_pools_with_throttle<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    dexes: Vec<Dex>,
+    provider: Arc<Provider<P>>,
+    requests_per_second_limit: usize,
+) -> Result<Vec<Pool>, PairSyncError<P>> {
+    filter_unverified_pools_with_context(
         pools,
         dexes,
-        usd_weth_pool,
-        weth_address,
-        usd_threshold,
-        provider,
-        0,
+        &FilterContext::new(provider, requests_per_second_limit),
     )
     .await
 }
 
-//Filter that removes pools with that contain less than a specified usd value
-pub async fn filter_pools_below_usd_threshold_with_throttle<P: 'static + JsonRpcClient>(
+pub(crate) async fn filter_unverified_pools_with_context<P: 'static + JsonRpcClient>(
     pools: Vec<Pool>,
     dexes: Vec<Dex>,
-    usd_weth_pool: Pool,
-    weth_address: H160,
-    usd_threshold: f64,
-    provider: Arc<Provider<P>>,
-    requests_per_second_limit: usize,
+    context: &FilterContext<P>,
 ) -> Result<Vec<Pool>, PairSyncError<P>> {
+    let provider = context.provider.clone();
+
     let multi_progress_bar = MultiProgress::new();
     let progress_bar = multi_progress_bar.add(ProgressBar::new(0));
     progress_bar.set_style(
-        ProgressStyle::with_template("{msg} {bar:40.cyan/blue} {pos:>7}/{len:7} Pools Filtered")
+        ProgressStyle::with_template("{msg} {bar:40.cyan/blue} {pos:>7}/{len:7} Pools Verified")
             .unwrap()
             .progress_chars("##-"),
     );
 
     progress_bar.set_length(pools.len() as u64);
-    progress_bar.set_message("Filtering pools: ");
+    progress_bar.set_message("Verifying pools: ");
 
     //Init a new vec to hold the filtered pools
     let mut filtered_pools = vec![];
 
-    let request_throttle = Arc::new(Mutex::new(RequestThrottle::new(requests_per_second_limit)));
+    let request_throttle = context.request_throttle.clone();
 
-    //Get price of weth in USD
-    let usd_price_per_weth = usd_weth_pool
-        .get_price(usd_weth_pool.a_to_b, provider.clone())
-        .await?;
-
-    //Initialize a Hashmap to keep track of token/weth prices already found to avoid unnecessary calls to the node
-    let token_weth_prices: Arc<Mutex<HashMap<H160, f64>>> = Arc::new(Mutex::new(HashMap::new()));
     let mut handles = vec![];
-    //For each pool, check if the usd value meets the specified threshold
     for pool in pools {
-        let token_weth_prices = token_weth_prices.clone();
         let request_throttle = request_throttle.clone();
         let provider = provider.clone();
         let dexes = dexes.clone();
         let progress_bar = progress_bar.clone();
 
         handles.push(tokio::spawn(async move {
-            let (token_a_reserves, token_b_reserves) = if pool.a_to_b {
-                (pool.reserve_0, pool.reserve_1)
-            } else {
-                (pool.reserve_1, pool.reserve_0)
-            };
-
-            let token_a_price_per_weth = token_weth_prices
-                .lock()
-                .unwrap()
-                .get(&pool.token_a)
-                .map(|price| price.to_owned());
-
-            progress_bar.inc(1);
-
-            let token_a_price_per_weth = match token_a_price_per_weth {
-                Some(price) => price,
-                None => {
-                    request_throttle.lock().unwrap().increment_or_sleep(1);
-                    let price = get_price_of_token_per_weth(
-                        pool.token_a,
-                        weth_address,
-                        &dexes,
-                        provider.clone(),
-                    )
-                    .await?;
-
-                    token_weth_prices
-                        .lock()
-                        .unwrap()
-                        .insert(pool.token_a, price);
-
-                    price
-                }
-            };
-
-            //Get weth value of token a in pool
-            let token_a_weth_value_in_pool = token_a_reserves as f64
-                / 10f64.powf(pool.token_a_decimals.into())
-                / token_a_price_per_weth;
-
-            //Calculate token_a usd value
-            let token_a_usd_value_in_pool = token_a_weth_value_in_pool * usd_price_per_weth;
-
-            let token_b_price_per_weth = token_weth_prices
-                .lock()
-                .unwrap()
-                .get(&pool.token_b)
-                .map(|price| price.to_owned());
-
-            let token_b_price_per_weth = match token_b_price_per_weth {
-                Some(price) => price.to_owned(),
-                None => {
-                    request_throttle.lock().unwrap().increment_or_sleep(1);
-                    let price = get_price_of_token_per_weth(
-                        pool.token_b,
-                        weth_address,
-                        &dexes,
-                        provider.clone(),
-                    )
-                    .await?;
-
-                    token_weth_prices
-                        .lock()
-                        .unwrap()
-                        .insert(pool.token_b, price);
-
-                    price
+            let verification_result = async {
+                for dex in dexes
+                    .iter()
+                    .filter(|dex| dex.pool_variant == pool.pool_variant)
+                {
+                    //Verification makes up to three calls, to the pool and to the factory
+                    request_throttle.lock().unwrap().increment_or_sleep(3);
+                    if dex.verify_pool(&pool, provider.clone()).await? {
+                        return Ok(true);
+                    }
                 }
-            };
-
-            //Get weth value of token a in pool
-            let token_b_weth_value_in_pool = token_b_reserves as f64
-                * 10f64.powf(pool.token_b_decimals.into())
-                / token_b_price_per_weth;
 
-            //Calculate token_b usd value
-            let token_b_usd_value_in_pool = token_b_weth_value_in_pool * usd_price_per_weth;
+                Ok::<_, PairSyncError<P>>(false)
+            }
+            .await;
 
-            //Compare the sum of token_a and token_b usd value against the specified threshold
-            let total_usd_value_in_pool = token_a_usd_value_in_pool + token_b_usd_value_in_pool;
-
-            Ok::<_, PairSyncError<P>>((total_usd_value_in_pool, pool))
+            progress_bar.inc(1);
+            (pool, verification_result)
         }));
     }
 
     for handle in handles {
         match handle.await {
-            Ok(filter_result) => match filter_result {
-                Ok((total_usd_value_in_pool, pool)) => {
-                    if usd_threshold <= total_usd_value_in_pool {
-                        filtered_pools.push(pool);
-                    }
-                }
-                Err(pair_sync_error) => match pair_sync_error {
-                    PairSyncError::PairDoesNotExistInDexes(_, _) => {}
-                    _ => return Err(pair_sync_error),
-                },
+            Ok((pool, verification_result)) => match verification_result {
+                Ok(true) => filtered_pools.push(pool),
+                Ok(false) => context.exclude_pool(pool, ExclusionReason::UnverifiedPool),
+                Err(pair_sync_error) => context.exclude_pool_with_error(pool, pair_sync_error)?,
             },
 
             Err(join_error) => return Err(PairSyncError::JoinError(join_error)),
@@ -227,61 +223,178 @@ pub async fn filter_pools_below_usd_threshold_with_throttle<P: 'static + JsonRpc
     Ok(filtered_pools)
 }
 
-async fn get_price_of_token_per_weth<P: 'static + JsonRpcClient>(
-    token_address: H160,
-    weth_address: H160,
-    dexes: &Vec<Dex>,
+//Filter that removes pools with that contain less than a specified value in the quote token.
+//Pools with a token that is not paired with the base token in the dexes are removed, see `tvl::get_pool_tvls`.
+pub async fn filter_pools_below_value_threshold<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    dexes: Vec<Dex>,
+    quote_token: QuoteToken,
+    threshold: f64,
     provider: Arc<Provider<P>>,
-) -> Result<f64, PairSyncError<P>> {
-    if token_address == weth_address {
-        return Ok(1.0);
-    }
-
-    //Get token_a/weth price
-    let token_a_weth_pool =
-        get_token_to_weth_pool(token_address, weth_address, dexes, provider.clone()).await?;
-
-    let token_a_price_per_weth = token_a_weth_pool
-        .get_price(token_a_weth_pool.token_a == weth_address, provider.clone())
-        .await?;
+) -> Result<Vec<Pool>, PairSyncError<P>> {
+    filter_pools_below_value_threshold_with_throttle(
+        pools,
+        dexes,
+        quote_token,
+        threshold,
+        provider,
+        0,
+    )
+    .await
+}
 
-    Ok(token_a_price_per_weth)
+//Filter that removes pools with that contain less than a specified value in the quote token
+pub async fn filter_pools_below_value_threshold_with_throttle<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    dexes: Vec<Dex>,
+    quote_token: QuoteToken,
+    threshold: f64,
+    provider: Arc<Provider<P>>,
+    requests_per_second_limit: usize,
+) -> Result<Vec<Pool>, PairSyncError<P>> {
+    filter_pools_below_value_threshold_with_context(
+        pools,
+        dexes,
+        &quote_token,
+        threshold,
+        &FilterContext::new(provider, requests_per_second_limit),
+    )
+    .await
 }
 
-//Gets the best token to weth pairing from the dexes provided
-async fn get_token_to_weth_pool<P: 'static + JsonRpcClient>(
-    token_a: H160,
-    weth_address: H160,
-    dexes: &Vec<Dex>,
+//Filter that removes pools with that contain less than a specified value in the quote token, also returning the reason each pool was removed.
+//Pools that fail a call to the node are removed and reported instead of returning the error.
+pub async fn filter_pools_below_value_threshold_with_report<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    dexes: Vec<Dex>,
+    quote_token: QuoteToken,
+    threshold: f64,
     provider: Arc<Provider<P>>,
-) -> Result<Pool, PairSyncError<P>> {
-    let mut token_a_weth_pool = Pool::empty_pool(PoolVariant::UniswapV2);
+    requests_per_second_limit: usize,
+) -> Result<(Vec<Pool>, FilterReport), PairSyncError<P>> {
+    let context = FilterContext::new(provider, requests_per_second_limit).with_report();
 
-    for dex in dexes {
-        (token_a_weth_pool.address, token_a_weth_pool.fee) = dex
-            .get_pool_with_best_liquidity(token_a, weth_address, provider.clone())
-            .await?;
+    let filtered_pools = filter_pools_below_value_threshold_with_context(
+        pools,
+        dexes,
+        &quote_token,
+        threshold,
+        &context,
+    )
+    .await?;
 
-        if !token_a_weth_pool.is_empty() {
-            break;
+    Ok((filtered_pools, context.take_report()))
+}
+
+pub(crate) async fn filter_pools_below_value_threshold_with_context<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    dexes: Vec<Dex>,
+    quote_token: &QuoteToken,
+    threshold: f64,
+    context: &FilterContext<P>,
+) -> Result<Vec<Pool>, PairSyncError<P>> {
+    let mut filtered_pools = vec![];
+
+    for (pool, token_values) in get_token_values(pools, dexes, quote_token, context).await? {
+        let pool_tvl = match token_values {
+            Ok(token_values) => PoolTvl::new(pool, token_values),
+            Err(pair_sync_error) => {
+                context.exclude_pool_with_error(pool, pair_sync_error)?;
+                continue;
+            }
+        };
+
+        //Pools with a token that has no route to the base token are removed
+        let unpriced_token = pool_tvl
+            .token_values
+            .iter()
+            .find(|token_value| token_value.price.is_none())
+            .map(|token_value| token_value.token);
+
+        if let Some(token) = unpriced_token {
+            context.exclude_pool(pool_tvl.pool, ExclusionReason::NoPriceRoute(token));
+        } else if pool_tvl.tvl < threshold {
+            context.exclude_pool(
+                pool_tvl.pool,
+                ExclusionReason::ValueBelowThreshold {
+                    value: pool_tvl.tvl,
+                    threshold,
+                },
+            );
+        } else {
+            filtered_pools.push(pool_tvl.pool);
         }
     }
 
-    if !token_a_weth_pool.is_empty() {
-        token_a_weth_pool.update_a_to_b(provider.clone()).await?;
-        token_a_weth_pool.update_reserves(provider).await?;
-    } else {
-        return Err(PairSyncError::PairDoesNotExistInDexes(
-            token_a,
-            weth_address,
-        ));
-    }
+    Ok(filtered_pools)
+}
 
-    Ok(token_a_weth_pool)
+//Filter that removes pools with that contain less than a specified usd value.
+//Pools are valued in weth and converted to USD with the usd/weth pool, see `filter_pools_below_value_threshold`.
+pub async fn filter_pools_below_usd_threshold<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    dexes: Vec<Dex>,
+    usd_weth_pool: Pool,
+    weth_address: H160,
+    usd_threshold: f64,
+    provider: Arc<Provider<P>>,
+) -> Result<Vec<Pool>, PairSyncError<P>> {
+    filter_pools_below_usd_threshold_with_throttle(
+        pools,
+        dexes,
+        usd_weth_pool,
+        weth_address,
+        usd_threshold,
+        provider,
+        0,
+    )
+    .await
+}
+
+//Filter that removes pools with that contain less than a specified usd value
+pub async fn filter_pools_below_usd_threshold_with_throttle<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    dexes: Vec<Dex>,
+    usd_weth_pool: Pool,
+    weth_address: H160,
+    usd_threshold: f64,
+    provider: Arc<Provider<P>>,
+    requests_per_second_limit: usize,
+) -> Result<Vec<Pool>, PairSyncError<P>> {
+    filter_pools_below_value_threshold_with_throttle(
+        pools,
+        dexes,
+        QuoteToken::new(weth_address).with_reference_pool(usd_weth_pool),
+        usd_threshold,
+        provider,
+        requests_per_second_limit,
+    )
+    .await
+}
+
+//Filter that removes pools with that contain less than a specified usd value, also returning the reason each pool was removed.
+//Pools that fail a call to the node are removed and reported instead of returning the error.
+pub async fn filter_pools_below_usd_threshold_with_report<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    dexes: Vec<Dex>,
+    usd_weth_pool: Pool,
+    weth_address: H160,
+    usd_threshold: f64,
+    provider: Arc<Provider<P>>,
+    requests_per_second_limit: usize,
+) -> Result<(Vec<Pool>, FilterReport), PairSyncError<P>> {
+    filter_pools_below_value_threshold_with_report(
+        pools,
+        dexes,
+        QuoteToken::new(weth_address).with_reference_pool(usd_weth_pool),
+        usd_threshold,
+        provider,
+        requests_per_second_limit,
+    )
+    .await
 }
 
 //Filter that removes pools with that contain less than a specified weth value
-//
 pub async fn filter_pools_below_weth_threshold<P: 'static + JsonRpcClient>(
     pools: Vec<Pool>,
     dexes: Vec<Dex>,
@@ -308,113 +421,347 @@ pub async fn filter_pools_below_weth_threshold_with_throttle<P: 'static + JsonRp
     provider: Arc<Provider<P>>,
     requests_per_second_limit: usize,
 ) -> Result<Vec<Pool>, PairSyncError<P>> {
-    //TODO: add progress bar
+    filter_pools_below_value_threshold_with_throttle(
+        pools,
+        dexes,
+        QuoteToken::new(weth_address),
+        weth_threshold,
+        provider,
+        requests_per_second_limit,
+    )
+    .await
+}
 
-    //Init a new vec to hold the filtered pools
+//Filter that removes pools with that contain less than a specified weth value, also returning the reason each pool was removed.
+//Pools that fail a call to the node are removed and reported instead of returning the error.
+pub async fn filter_pools_below_weth_threshold_with_report<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    dexes: Vec<Dex>,
+    weth_address: H160,
+    weth_threshold: f64,
+    provider: Arc<Provider<P>>,
+    requests_per_second_limit: usize,
+) -> Result<(Vec<Pool>, FilterReport), PairSyncError<P>> {
+    filter_pools_below_value_threshold_with_report(
+        pools,
+        dexes,
+        QuoteToken::new(weth_address),
+        weth_threshold,
+        provider,
+        requests_per_second_limit,
+    )
+    .await
+}
+
+//Gets the price of one token in the base token, ie. weth, using the best pool pairing them in the dexes
+pub(crate) async fn get_token_price<P: 'static + JsonRpcClient>(
+    token_address: H160,
+    base_token: H160,
+    dexes: &Vec<Dex>,
+    provider: Arc<Provider<P>>,
+    request_throttle: Arc<Mutex<RequestThrottle>>,
+) -> Result<f64, PairSyncError<P>> {
+    if token_address == base_token {
+        return Ok(1.0);
+    }
+
+    let base_token_pool = get_token_to_base_token_pool(
+        token_address,
+        base_token,
+        dexes,
+        provider.clone(),
+        request_throttle,
+    )
+    .await?;
+
+    //`get_price(a_per_b)` prices token_b in units of token_a, so the token is priced in the base token when the base token is token_a
+    let token_price = base_token_pool
+        .get_price(base_token_pool.token_a == base_token, provider.clone())
+        .await?;
+
+    Ok(token_price)
+}
+
+//Gets the best token to base token pairing from the dexes provided
+async fn get_token_to_base_token_pool<P: 'static + JsonRpcClient>(
+    token_a: H160,
+    base_token: H160,
+    dexes: &Vec<Dex>,
+    provider: Arc<Provider<P>>,
+    request_throttle: Arc<Mutex<RequestThrottle>>,
+) -> Result<Pool, PairSyncError<P>> {
+    for dex in dexes {
+        let (pool_address, fee) = dex
+            .get_pool_with_best_liquidity_with_request_throttle(
+                token_a,
+                base_token,
+                provider.clone(),
+                request_throttle.clone(),
+            )
+            .await?;
+
+        //Populate the pool using the dex variant so that the pool is priced with the correct math
+        if !pool_address.is_zero() {
+            return Pool::new_pool_from_address(pool_address, fee, dex.pool_variant, provider)
+                .await;
+        }
+    }
+
+    Err(PairSyncError::PairDoesNotExistInDexes(token_a, base_token))
+}
+
+//Filter to remove tokens that incorporate fees on transfer.
+//This filter determines fee on transfer tokens by simulating a transfer and checking if the recieved amount is less
+//than the sent amount. It can not be guaranteed that all fee tokens are filtered out. For example,
+//if a token has a fee mechanic but the fee is set to 0, this filter will not remove the token.
+//Pools are removed if any of their tokens has a transfer tax above `tax_threshold`, ie. 0.01 for 1%.
+//The measured tax of each token is returned alongside the filtered pools.
+pub async fn filter_fee_tokens<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    tax_threshold: f64,
+    provider: Arc<Provider<P>>,
+) -> Result<(Vec<Pool>, HashMap<H160, TransferTax>), PairSyncError<P>> {
+    filter_fee_tokens_with_throttle(pools, tax_threshold, provider, 0).await
+}
+
+//Filter to remove tokens that incorporate fees on transfer, see `filter_fee_tokens`.
+pub async fn filter_fee_tokens_with_throttle<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    tax_threshold: f64,
+    provider: Arc<Provider<P>>,
+    requests_per_second_limit: usize,
+) -> Result<(Vec<Pool>, HashMap<H160, TransferTax>), PairSyncError<P>> {
+    filter_fee_tokens_with_context(
+        pools,
+        tax_threshold,
+        &FilterContext::new(provider, requests_per_second_limit),
+    )
+    .await
+}
+
+pub(crate) async fn filter_fee_tokens_with_context<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    tax_threshold: f64,
+    context: &FilterContext<P>,
+) -> Result<(Vec<Pool>, HashMap<H160, TransferTax>), PairSyncError<P>> {
+    let provider = context.provider.clone();
+
+    let multi_progress_bar = MultiProgress::new();
+    let progress_bar = multi_progress_bar.add(ProgressBar::new(0));
+    progress_bar.set_style(
+        ProgressStyle::with_template("{msg} {bar:40.cyan/blue} {pos:>7}/{len:7} Tokens Simulated")
+            .unwrap()
+            .progress_chars("##-"),
+    );
+
+    let request_throttle = context.request_throttle.clone();
+
+    //Each token is simulated once, from the pool that holds the largest balance of the token
+    let mut token_pools: HashMap<H160, (u128, Pool)> = HashMap::new();
+    for pool in pools.iter() {
+        for (token, balance) in pool.tokens.iter().zip(pool.balances.iter()) {
+            match token_pools.get(token) {
+                Some((best_balance, _)) if best_balance >= balance => {}
+                _ => {
+                    token_pools.insert(*token, (*balance, pool.clone()));
+                }
+            }
+        }
+    }
+
+    progress_bar.set_length(token_pools.len() as u64);
+    progress_bar.set_message("Simulating token transfers: ");
+
+    let mut handles = vec![];
+    for (token, (_, pool)) in token_pools {
+        let request_throttle = request_throttle.clone();
+        let provider = provider.clone();
+        let progress_bar = progress_bar.clone();
+
+        handles.push(tokio::spawn(async move {
+            request_throttle.lock().unwrap().increment_or_sleep(1);
+            let simulation_result = simulate_pool_transfer_tax(&pool, token, provider).await;
+
+            progress_bar.inc(1);
+            (token, simulation_result)
+        }));
+    }
+
+    let mut transfer_taxes = HashMap::new();
+    //Tokens where the simulation failed, only collected if reporting is enabled
+    let mut failed_tokens = HashMap::new();
+    for handle in handles {
+        match handle.await {
+            Ok((token, simulation_result)) => match simulation_result {
+                Ok(Some(transfer_tax)) => {
+                    transfer_taxes.insert(token, transfer_tax);
+                }
+                Ok(None) => {}
+                Err(pair_sync_error) => {
+                    if context.report.is_none() {
+                        return Err(pair_sync_error);
+                    }
+
+                    failed_tokens.insert(token, format!("{:?}", pair_sync_error));
+                }
+            },
+
+            Err(join_error) => return Err(PairSyncError::JoinError(join_error)),
+        }
+    }
+
+    //Tokens that could not be simulated because no pool holds a balance are kept
     let mut filtered_pools = vec![];
+    for pool in pools {
+        let taxed_token = pool.tokens.iter().find_map(|token| {
+            transfer_taxes
+                .get(token)
+                .filter(|transfer_tax| transfer_tax.max_tax() > tax_threshold)
+                .map(|transfer_tax| (*token, transfer_tax.max_tax()))
+        });
+
+        if let Some((token, tax)) = taxed_token {
+            context.exclude_pool(pool, ExclusionReason::TransferTax { token, tax });
+        } else if let Some(error) = pool
+            .tokens
+            .iter()
+            .find_map(|token| failed_tokens.get(token))
+        {
+            let reason = ExclusionReason::RequestError(error.clone());
+            context.exclude_pool(pool, reason);
+        } else {
+            filtered_pools.push(pool);
+        }
+    }
+
+    Ok((filtered_pools, transfer_taxes))
+}
+
+//Filter that removes pools where a token can be bought but not sold back.
+//For each token in the pool, a small buy with the pool's other token is swapped through the pool and immediately sold back
+//in an `eth_call`, see `simulation::simulate_round_trip`. Tokens paired with or held as native ether are not simulated. Pools are removed if any round trip loses more than `max_loss`,
+//ie. 0.05 for 5%, so the threshold should be set above the pool's fees.
+pub async fn filter_honeypots<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    max_loss: f64,
+    provider: Arc<Provider<P>>,
+) -> Result<Vec<Pool>, PairSyncError<P>> {
+    filter_honeypots_with_throttle(pools, max_loss, provider, 0).await
+}
+
+//Filter that removes pools where a token can be bought but not sold back, see `filter_honeypots`.
+pub async fn filter_honeypots_with_throttle<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    max_loss: f64,
+    provider: Arc<Provider<P>>,
+    requests_per_second_limit: usize,
+) -> Result<Vec<Pool>, PairSyncError<P>> {
+    filter_honeypots_with_context(
+        pools,
+        max_loss,
+        &FilterContext::new(provider, requests_per_second_limit),
+    )
+    .await
+}
 
-    let request_throttle = Arc::new(Mutex::new(RequestThrottle::new(requests_per_second_limit)));
+pub(crate) async fn filter_honeypots_with_context<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    max_loss: f64,
+    context: &FilterContext<P>,
+) -> Result<Vec<Pool>, PairSyncError<P>> {
+    let provider = context.provider.clone();
+
+    let multi_progress_bar = MultiProgress::new();
+    let progress_bar = multi_progress_bar.add(ProgressBar::new(0));
+    progress_bar.set_style(
+        ProgressStyle::with_template("{msg} {bar:40.cyan/blue} {pos:>7}/{len:7} Pools Simulated")
+            .unwrap()
+            .progress_chars("##-"),
+    );
+
+    progress_bar.set_length(pools.len() as u64);
+    progress_bar.set_message("Simulating round trips: ");
+
+    let request_throttle = context.request_throttle.clone();
 
-    //Initialize a Hashmap to keep track of token/weth prices already found to avoid unnecessary calls to the node
-    let token_weth_prices: Arc<Mutex<HashMap<H160, f64>>> = Arc::new(Mutex::new(HashMap::new()));
     let mut handles = vec![];
-    //For each pool, check if the usd value meets the specified threshold
     for pool in pools {
-        let token_weth_prices = token_weth_prices.clone();
         let request_throttle = request_throttle.clone();
+        let balance_slots = context.balance_slots.clone();
         let provider = provider.clone();
-        let dexes = dexes.clone();
+        let progress_bar = progress_bar.clone();
 
         handles.push(tokio::spawn(async move {
-            let (token_a_reserves, token_b_reserves) = if pool.a_to_b {
-                (pool.reserve_0, pool.reserve_1)
-            } else {
-                (pool.reserve_1, pool.reserve_0)
-            };
-
-            let token_a_price_per_weth = token_weth_prices
-                .lock()
-                .unwrap()
-                .get(&pool.token_a)
-                .map(|price| price.to_owned());
-
-            let token_a_price_per_weth = match token_a_price_per_weth {
-                Some(price) => price,
-                None => {
-                    request_throttle.lock().unwrap().increment_or_sleep(1);
-                    let price = get_price_of_token_per_weth(
-                        pool.token_a,
-                        weth_address,
-                        &dexes,
-                        provider.clone(),
-                    )
-                    .await?;
-
-                    token_weth_prices
+            let simulation_result = async {
+                for token in pool.tokens.iter() {
+                    //Buy the token with the other token the pool holds the most of
+                    let quote_token = pool
+                        .tokens
+                        .iter()
+                        .zip(pool.balances.iter())
+                        .filter(|(quote_token, _)| *quote_token != token)
+                        .max_by_key(|(_, balance)| **balance)
+                        .map(|(quote_token, _)| *quote_token);
+
+                    let quote_token = match quote_token {
+                        Some(quote_token)
+                            if !is_native_token(*token) && !is_native_token(quote_token) =>
+                        {
+                            quote_token
+                        }
+                        _ => continue,
+                    };
+
+                    //The quote token's balance slot is found once and shared by every pool quoted in it
+                    let balance_slot_cell = balance_slots
                         .lock()
                         .unwrap()
-                        .insert(pool.token_a, price);
+                        .entry(quote_token)
+                        .or_default()
+                        .clone();
+
+                    let balance_slot = *balance_slot_cell
+                        .get_or_try_init(|| async {
+                            //An access list and a call for each slot read by balanceOf, usually one
+                            request_throttle.lock().unwrap().increment_or_sleep(2);
+                            find_simulator_balance_slot(quote_token, provider.clone()).await
+                        })
+                        .await?;
 
-                    price
-                }
-            };
-
-            //Get weth value of token a in pool
-            let token_a_weth_value_in_pool = token_a_reserves as f64
-                / 10f64.powf(pool.token_a_decimals.into())
-                / token_a_price_per_weth;
-
-            let token_b_price_per_weth = token_weth_prices
-                .lock()
-                .unwrap()
-                .get(&pool.token_b)
-                .map(|price| price.to_owned());
-
-            let token_b_price_per_weth = match token_b_price_per_weth {
-                Some(price) => price.to_owned(),
-                None => {
                     request_throttle.lock().unwrap().increment_or_sleep(1);
-                    let price = get_price_of_token_per_weth(
-                        pool.token_b,
-                        weth_address,
-                        &dexes,
+                    if let Some(loss) = simulate_round_trip_with_balance_slot(
+                        &pool,
+                        *token,
+                        quote_token,
+                        balance_slot,
                         provider.clone(),
                     )
-                    .await?;
-
-                    token_weth_prices
-                        .lock()
-                        .unwrap()
-                        .insert(pool.token_b, price);
-
-                    price
+                    .await?
+                    {
+                        if loss > max_loss {
+                            return Ok(Some((*token, loss)));
+                        }
+                    }
                 }
-            };
 
-            //Get weth value of token a in pool
-            let token_b_weth_value_in_pool = token_b_reserves as f64
-                / 10f64.powf(pool.token_b_decimals.into())
-                / token_b_price_per_weth;
+                Ok::<_, PairSyncError<P>>(None)
+            }
+            .await;
 
-            //Compare the sum of token_a and token_b usd value against the specified threshold
-            let total_weth_value_in_pool = token_a_weth_value_in_pool + token_b_weth_value_in_pool;
-
-            Ok::<_, PairSyncError<P>>((total_weth_value_in_pool, pool))
+            progress_bar.inc(1);
+            (pool, simulation_result)
         }));
     }
 
+    let mut filtered_pools = vec![];
     for handle in handles {
         match handle.await {
-            Ok(filter_result) => match filter_result {
-                Ok((total_weth_value_in_pool, pool)) => {
-                    if weth_threshold <= total_weth_value_in_pool {
-                        filtered_pools.push(pool);
-                    }
+            Ok((pool, simulation_result)) => match simulation_result {
+                Ok(None) => filtered_pools.push(pool),
+                Ok(Some((token, loss))) => {
+                    context.exclude_pool(pool, ExclusionReason::Honeypot { token, loss })
                 }
-                Err(pair_sync_error) => match pair_sync_error {
-                    PairSyncError::PairDoesNotExistInDexes(_, _) => {}
-                    _ => return Err(pair_sync_error),
-                },
+                Err(pair_sync_error) => context.exclude_pool_with_error(pool, pair_sync_error)?,
             },
 
             Err(join_error) => return Err(PairSyncError::JoinError(join_error)),
@@ -424,9 +771,112 @@ pub async fn filter_pools_below_weth_threshold_with_throttle<P: 'static + JsonRp
     Ok(filtered_pools)
 }
 
-//Filter to remove tokens that incorporate fees on transfer.
-//This filter determines fee on transfer tokens by simulating a transfer and checking if the recieved amount is less
-//than the sent amount. It can not be guaranteed that all fee tokens are filtered out. For example,
-//if a token has a fee mechanic but the fee is set to 0, this filter will not remove the token.
-#[allow(dead_code)]
-fn filter_fee_tokens<P: 'static + JsonRpcClient>(_provider: Arc<Provider<P>>) {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool::PoolVariant;
+
+    fn token(byte: u8) -> H160 {
+        H160::repeat_byte(byte)
+    }
+
+    fn pool(address: u8, token_a: u8, token_b: u8) -> Pool {
+        Pool::new(
+            token(address),
+            token(token_a),
+            18,
+            token(token_b),
+            18,
+            true,
+            0,
+            0,
+            300,
+            PoolVariant::UniswapV2,
+        )
+    }
+
+    //Curve pool holding every token, with token_a and token_b unset
+    fn curve_pool(address: u8, tokens: &[u8]) -> Pool {
+        let mut pool = Pool::new_curve_pool(token(address));
+        pool.tokens = tokens.iter().map(|byte| token(*byte)).collect();
+        pool
+    }
+
+    fn addresses(pools: &[Pool]) -> Vec<H160> {
+        pools.iter().map(|pool| pool.address).collect()
+    }
+
+    #[test]
+    fn test_filter_pools_outside_allowlist() {
+        let pools = vec![
+            pool(10, 1, 2),
+            pool(11, 1, 3),
+            pool(12, 3, 4),
+            curve_pool(13, &[3, 4, 1]),
+            curve_pool(14, &[1, 2]),
+        ];
+        let allowlist = vec![token(1), token(2)];
+
+        let kept_pools = filter_pools_outside_allowlist(
+            pools.clone(),
+            allowlist.clone(),
+            AllowlistMode::AnyToken,
+        );
+        assert_eq!(
+            addresses(&kept_pools),
+            vec![token(10), token(11), token(13), token(14)]
+        );
+
+        let kept_pools = filter_pools_outside_allowlist(pools, allowlist, AllowlistMode::AllTokens);
+        assert_eq!(addresses(&kept_pools), vec![token(10), token(14)]);
+    }
+
+    #[test]
+    fn test_get_disallowed_token() {
+        let allowlist: HashSet<H160> = [token(1), token(2)].into_iter().collect();
+
+        //Pools without an allowlisted token return their first token in any token mode
+        assert_eq!(
+            get_disallowed_token(&pool(10, 3, 4), &allowlist, AllowlistMode::AnyToken),
+            Some(token(3))
+        );
+        assert_eq!(
+            get_disallowed_token(&pool(10, 3, 1), &allowlist, AllowlistMode::AnyToken),
+            None
+        );
+
+        //The first token that is not allowlisted is returned in all tokens mode
+        assert_eq!(
+            get_disallowed_token(&pool(10, 1, 4), &allowlist, AllowlistMode::AllTokens),
+            Some(token(4))
+        );
+
+        //The pool's tokens are checked instead of the unset token_a and token_b
+        let curve_pool = curve_pool(13, &[1, 2, 5]);
+        assert_eq!(
+            get_disallowed_token(&curve_pool, &allowlist, AllowlistMode::AnyToken),
+            None
+        );
+        assert_eq!(
+            get_disallowed_token(&curve_pool, &allowlist, AllowlistMode::AllTokens),
+            Some(token(5))
+        );
+    }
+
+    #[test]
+    fn test_filter_blacklisted_tokens() {
+        let pools = vec![
+            pool(10, 1, 2),
+            pool(11, 3, 4),
+            curve_pool(12, &[3, 4, 5]),
+            curve_pool(13, &[3, 4, 6]),
+        ];
+
+        //Tokens of multi token pools beyond token_a and token_b are checked
+        let kept_pools = filter_blacklisted_tokens(pools.clone(), vec![token(2), token(5)]);
+        assert_eq!(addresses(&kept_pools), vec![token(11), token(13)]);
+
+        let kept_pools = filter_blacklisted_addresses(pools, vec![token(1), token(6), token(11)]);
+        assert_eq!(addresses(&kept_pools), vec![token(12)]);
+    }
+}
@@ -1,7 +1,18 @@
 mod abi;
+pub mod chainlink;
+pub mod config;
 pub mod dex;
 pub mod error;
 pub mod filter;
+mod math;
+pub mod oracle;
+pub mod pipeline;
 pub mod pool;
+pub mod registry;
+pub mod report;
+pub mod simulation;
 pub mod sync;
 mod throttle;
+pub mod token_list;
+pub mod tvl;
+pub mod usd;
@@ -0,0 +1,208 @@
+use ethers::{abi::ethereum_types::U512, types::U256};
+
+use crate::error::SwapSimulationError;
+
+use super::{constant_product, u256_to_f64};
+
+//Concentrated liquidity fees are expressed in hundredths of a bip
+const FEE_DENOMINATOR: u32 = 1_000_000;
+
+//Calculates the virtual reserves of a concentrated liquidity pool at its current price.
+//Within the current tick the pool behaves like a constant product pool with these reserves.
+pub fn get_virtual_reserves(liquidity: u128, sqrt_price_x96: U256) -> (u128, u128) {
+    if sqrt_price_x96.is_zero() || liquidity == 0 {
+        return (0, 0);
+    }
+
+    let liquidity = U256::from(liquidity);
+    let q96 = U256::one() << 96;
+
+    //reserve_0 = L / sqrt(P) and reserve_1 = L * sqrt(P), where sqrt(P) is a Q64.96 number
+    let reserve_0 = U512::from(liquidity * q96) / U512::from(sqrt_price_x96);
+    let reserve_1 = liquidity.full_mul(sqrt_price_x96) / U512::from(q96);
+
+    (saturating_u128(reserve_0), saturating_u128(reserve_1))
+}
+
+//Calculates the amount out of a swap against the virtual reserves at the current price. This is a single tick
+//approximation: ticks and their liquidity are not synced, so the swap is only simulated while the price stays within
+//the tick spacing range around the current price, where the liquidity can not change. Swaps that would move the price
+//out of that range return `SwapSimulationError::TickRangeExceeded` instead of an inexact amount.
+//A tick spacing of zero is treated as one, so only the current tick is used.
+pub fn get_amount_out_within_tick(
+    amount_in: U256,
+    reserve_in: u128,
+    reserve_out: u128,
+    zero_for_one: bool,
+    fee: u32,
+    tick_spacing: i32,
+) -> Result<U256, SwapSimulationError> {
+    let fee_complement = FEE_DENOMINATOR
+        .checked_sub(fee)
+        .ok_or(SwapSimulationError::InvalidFee(fee))?;
+
+    let amount_out = constant_product::get_amount_out(
+        amount_in,
+        U256::from(reserve_in),
+        U256::from(reserve_out),
+        fee,
+        FEE_DENOMINATOR,
+    )?;
+
+    //The fee is not added to the liquidity, so only the amount in after fees moves the price
+    let amount_in_less_fee = amount_in * U256::from(fee_complement) / U256::from(FEE_DENOMINATOR);
+    let reserve_in_after = reserve_in as f64 + u256_to_f64(amount_in_less_fee);
+    let reserve_out_after = reserve_out as f64 - u256_to_f64(amount_out);
+
+    //Prices are in token1 per token0, as with ticks
+    let (price, price_after) = if zero_for_one {
+        (
+            reserve_out as f64 / reserve_in as f64,
+            reserve_out_after / reserve_in_after,
+        )
+    } else {
+        (
+            reserve_in as f64 / reserve_out as f64,
+            reserve_in_after / reserve_out_after,
+        )
+    };
+
+    let (tick_lower, tick_upper) = get_tick_range(price, tick_spacing);
+
+    let within_tick = if zero_for_one {
+        price_after >= get_price_at_tick(tick_lower)
+    } else {
+        price_after < get_price_at_tick(tick_upper)
+    };
+
+    if !within_tick || !price_after.is_finite() {
+        return Err(SwapSimulationError::TickRangeExceeded);
+    }
+
+    Ok(amount_out)
+}
+
+//Gets the ticks at the bounds of the tick spacing range holding the price
+fn get_tick_range(price: f64, tick_spacing: i32) -> (i32, i32) {
+    let tick_spacing = tick_spacing.max(1);
+    let tick = (price.ln() / 1.0001f64.ln()).floor() as i32;
+    let tick_lower = tick.div_euclid(tick_spacing) * tick_spacing;
+
+    (tick_lower, tick_lower + tick_spacing)
+}
+
+fn get_price_at_tick(tick: i32) -> f64 {
+    1.0001f64.powi(tick)
+}
+
+fn saturating_u128(value: U512) -> u128 {
+    if value > U512::from(u128::MAX) {
+        u128::MAX
+    } else {
+        value.as_u128()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_virtual_reserves() {
+        let q96 = U256::one() << 96;
+        let liquidity = 1_000_000_000_000_000_000u128;
+
+        //A price of one gives equal reserves of the liquidity
+        assert_eq!(get_virtual_reserves(liquidity, q96), (liquidity, liquidity));
+
+        //A price of four halves reserve_0 and doubles reserve_1
+        assert_eq!(
+            get_virtual_reserves(liquidity, q96 * 2),
+            (liquidity / 2, liquidity * 2)
+        );
+
+        //A sqrt price of a USDC/WETH pool, where one WETH is priced at around 2000 USDC
+        assert_eq!(
+            get_virtual_reserves(
+                12_345_678_901_234_567_890,
+                U256::from_dec_str("1771595571142957166518320255467520").unwrap()
+            ),
+            (552_115_545_030_908, 276_057_772_515_454_071_246_523)
+        );
+    }
+
+    #[test]
+    fn test_get_amount_out_within_tick() {
+        let q96 = U256::one() << 96;
+        let liquidity = 1_000_000_000_000_000_000u128;
+
+        //A price of one is at the lower bound of the tick range [0, 60)
+        let (reserve_0, reserve_1) = get_virtual_reserves(liquidity, q96);
+
+        //0.1% of the reserves moves the price by about 0.2%, within the range
+        let amount_out =
+            get_amount_out_within_tick(U256::exp10(15), reserve_1, reserve_0, false, 3000, 60)
+                .unwrap();
+        assert_eq!(
+            amount_out,
+            constant_product::get_amount_out(
+                U256::exp10(15),
+                U256::from(reserve_1),
+                U256::from(reserve_0),
+                3000,
+                1_000_000
+            )
+            .unwrap()
+        );
+
+        //1% of the reserves moves the price by about 2%, past tick 60
+        assert!(matches!(
+            get_amount_out_within_tick(U256::exp10(16), reserve_1, reserve_0, false, 3000, 60),
+            Err(SwapSimulationError::TickRangeExceeded)
+        ));
+
+        //Any swap of token0 moves the price below tick 0
+        assert!(matches!(
+            get_amount_out_within_tick(U256::exp10(15), reserve_0, reserve_1, true, 3000, 60),
+            Err(SwapSimulationError::TickRangeExceeded)
+        ));
+
+        //A price of 1.003 is around tick 30, so swaps of token0 can move the price down to tick 0
+        let (reserve_0, reserve_1) =
+            get_virtual_reserves(liquidity, q96 * U256::from(10015) / U256::from(10000));
+        assert!(
+            get_amount_out_within_tick(U256::exp10(15), reserve_0, reserve_1, true, 3000, 60)
+                .is_ok()
+        );
+        assert!(matches!(
+            get_amount_out_within_tick(U256::exp10(16), reserve_0, reserve_1, true, 3000, 60),
+            Err(SwapSimulationError::TickRangeExceeded)
+        ));
+    }
+
+    #[test]
+    fn test_get_amount_out_within_tick_invalid_fee() {
+        assert!(matches!(
+            get_amount_out_within_tick(U256::one(), 1000, 1000, true, 1_000_001, 60),
+            Err(SwapSimulationError::InvalidFee(1_000_001))
+        ));
+    }
+
+    #[test]
+    fn test_get_tick_range() {
+        assert_eq!(get_tick_range(1.0, 60), (0, 60));
+        assert_eq!(get_tick_range(1.0001f64.powi(61) * 1.00001, 60), (60, 120));
+        assert_eq!(get_tick_range(1.0001f64.powi(-5) * 1.00001, 10), (-10, 0));
+        assert_eq!(get_tick_range(1.0001f64.powi(7) * 1.00001, 0), (7, 8));
+    }
+
+    #[test]
+    fn test_get_virtual_reserves_bounds() {
+        assert_eq!(get_virtual_reserves(0, U256::one() << 96), (0, 0));
+        assert_eq!(get_virtual_reserves(1000, U256::zero()), (0, 0));
+
+        //Reserves above u128::MAX at the minimum sqrt price saturate instead of overflowing
+        let (reserve_0, _) = get_virtual_reserves(u128::MAX, U256::from(4_295_128_739u64));
+        assert_eq!(reserve_0, u128::MAX);
+    }
+}
@@ -0,0 +1,61 @@
+use ethers::types::U256;
+
+use crate::error::SwapSimulationError;
+
+//Calculates the amount out for an x * y = k pool, where the fee is expressed as a fraction of `fee_denominator`
+pub fn get_amount_out(
+    amount_in: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    fee: u32,
+    fee_denominator: u32,
+) -> Result<U256, SwapSimulationError> {
+    if reserve_in.is_zero() || reserve_out.is_zero() {
+        return Err(SwapSimulationError::InsufficientLiquidity);
+    }
+
+    let fee_complement = fee_denominator
+        .checked_sub(fee)
+        .ok_or(SwapSimulationError::InvalidFee(fee))?;
+
+    let amount_in_with_fee = amount_in * U256::from(fee_complement);
+    let numerator = amount_in_with_fee * reserve_out;
+    let denominator = reserve_in * U256::from(fee_denominator) + amount_in_with_fee;
+
+    Ok(numerator / denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_amount_out() {
+        //1 token into a 1,000 / 2,000 pair with a 0.3% fee
+        assert_eq!(
+            get_amount_out(
+                U256::exp10(18),
+                U256::exp10(21),
+                U256::exp10(21) * 2,
+                300,
+                100_000
+            )
+            .unwrap(),
+            U256::from_dec_str("1992013962079806432").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_amount_out_invalid() {
+        assert!(matches!(
+            get_amount_out(U256::one(), U256::zero(), U256::one(), 300, 100_000),
+            Err(SwapSimulationError::InsufficientLiquidity)
+        ));
+
+        //A fee above the denominator returns an error instead of underflowing
+        assert!(matches!(
+            get_amount_out(U256::one(), U256::one(), U256::one(), 100_001, 100_000),
+            Err(SwapSimulationError::InvalidFee(100_001))
+        ));
+    }
+}
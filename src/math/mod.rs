@@ -0,0 +1,16 @@
+pub mod concentrated_liquidity;
+pub mod constant_product;
+pub mod solidly;
+pub mod stable_swap;
+pub mod weighted;
+
+use ethers::types::U256;
+
+//Converts an amount to f64 without the u128 conversion panicking for amounts above u128::MAX
+pub(crate) fn u256_to_f64(value: U256) -> f64 {
+    value
+        .0
+        .iter()
+        .rev()
+        .fold(0.0, |acc, limb| acc * 2f64.powi(64) + *limb as f64)
+}
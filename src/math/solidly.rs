@@ -0,0 +1,163 @@
+use ethers::types::U256;
+
+use crate::error::SwapSimulationError;
+
+//Maximum number of Newton iterations, matching the Solidly pair contract
+const MAX_ITERATIONS: usize = 255;
+
+//Calculates the amount out for a Solidly stable pair using the x^3 * y + y^3 * x curve, mirroring `Pair._getAmountOut`.
+//The fee must already be deducted from `amount_in`.
+pub fn get_amount_out_stable(
+    amount_in: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    decimals_in: u8,
+    decimals_out: u8,
+) -> Result<U256, SwapSimulationError> {
+    if reserve_in.is_zero() || reserve_out.is_zero() {
+        return Err(SwapSimulationError::InsufficientLiquidity);
+    }
+
+    let precision = U256::exp10(18);
+    let decimals_in = U256::exp10(decimals_in.into());
+    let decimals_out = U256::exp10(decimals_out.into());
+
+    //Normalize the reserves and amount in to 18 decimals
+    let reserve_in = reserve_in * precision / decimals_in;
+    let reserve_out = reserve_out * precision / decimals_out;
+    let amount_in = amount_in * precision / decimals_in;
+
+    let xy = k(reserve_in, reserve_out);
+    let y = reserve_out - get_y(amount_in + reserve_in, xy, reserve_out)?;
+
+    Ok(y * decimals_out / precision)
+}
+
+//Invariant of a stable pair for reserves normalized to 18 decimals
+fn k(x: U256, y: U256) -> U256 {
+    let precision = U256::exp10(18);
+
+    let a = x * y / precision;
+    let b = x * x / precision + y * y / precision;
+
+    a * b / precision
+}
+
+fn f(x_0: U256, y: U256) -> U256 {
+    let precision = U256::exp10(18);
+
+    x_0 * (y * y / precision * y / precision) / precision
+        + (x_0 * x_0 / precision * x_0 / precision) * y / precision
+}
+
+fn d(x_0: U256, y: U256) -> U256 {
+    let precision = U256::exp10(18);
+
+    U256::from(3) * x_0 * (y * y / precision) / precision
+        + (x_0 * x_0 / precision * x_0 / precision)
+}
+
+//Solves for the reserve out that keeps the invariant at `xy` given the new reserve in `x_0`
+fn get_y(x_0: U256, xy: U256, mut y: U256) -> Result<U256, SwapSimulationError> {
+    let precision = U256::exp10(18);
+
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        let k = f(x_0, y);
+        let derivative = d(x_0, y);
+
+        if derivative.is_zero() {
+            return Err(SwapSimulationError::InsufficientLiquidity);
+        }
+
+        if k < xy {
+            y += (xy - k) * precision / derivative;
+        } else {
+            let dy = (k - xy) * precision / derivative;
+            if dy > y {
+                return Err(SwapSimulationError::InsufficientLiquidity);
+            }
+            y -= dy;
+        }
+
+        let diff = if y > y_prev { y - y_prev } else { y_prev - y };
+        if diff <= U256::one() {
+            return Ok(y);
+        }
+    }
+
+    Err(SwapSimulationError::InvariantDidNotConverge)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_amount_out_stable() {
+        //1,000 USDC into a balanced 10M USDC / 10M DAI pair
+        assert_eq!(
+            get_amount_out_stable(
+                U256::from(1_000_000_000u64),
+                U256::from(10_000_000_000_000u64),
+                U256::exp10(25),
+                6,
+                18
+            )
+            .unwrap(),
+            U256::from_dec_str("999999999999500000000").unwrap()
+        );
+
+        //Imbalanced pairs give more of the abundant token out
+        assert_eq!(
+            get_amount_out_stable(
+                U256::exp10(20),
+                U256::exp10(24),
+                U256::exp10(24) * 2,
+                18,
+                18
+            )
+            .unwrap(),
+            U256::from_dec_str("107689850178935754361").unwrap()
+        );
+
+        //Large swaps move along the curve, swapping half the reserve in
+        assert_eq!(
+            get_amount_out_stable(
+                U256::from(5_000_000_000_000u64),
+                U256::from(10_000_000_000_000u64),
+                U256::from(10_000_000_000_000u64),
+                6,
+                6
+            )
+            .unwrap(),
+            U256::from(4_726_043_370_813u64)
+        );
+    }
+
+    #[test]
+    fn test_get_y() {
+        let precision = U256::exp10(18);
+        let reserve = U256::exp10(24);
+        let xy = k(reserve, reserve);
+
+        //The reserve out is unchanged when the reserve in is unchanged
+        let y = get_y(reserve, xy, reserve).unwrap();
+        assert!(y <= reserve + 1 && y + 1 >= reserve);
+
+        //The new reserves keep the invariant within rounding
+        let x_0 = reserve + precision * 1000;
+        let y = get_y(x_0, xy, reserve).unwrap();
+        assert!(y < reserve);
+        let k_1 = k(x_0, y);
+        assert!(k_1 < xy + precision && xy < k_1 + precision);
+    }
+
+    #[test]
+    fn test_get_amount_out_stable_empty_reserves() {
+        assert!(matches!(
+            get_amount_out_stable(U256::one(), U256::zero(), U256::exp10(18), 18, 18),
+            Err(SwapSimulationError::InsufficientLiquidity)
+        ));
+    }
+}
@@ -0,0 +1,250 @@
+use ethers::types::U256;
+
+use crate::error::SwapSimulationError;
+
+//Curve stores rates and virtual prices with 18 decimals of precision
+pub const PRECISION: u128 = 1_000_000_000_000_000_000;
+//Curve pool fees are expressed as a fraction of 1e10
+pub const FEE_DENOMINATOR: u64 = 10_000_000_000;
+
+//Maximum number of Newton iterations, matching the Curve contracts
+const MAX_ITERATIONS: usize = 255;
+
+//Normalizes pool balances to 18 decimals using the rate of each coin
+pub fn xp(balances: &[U256], rates: &[U256]) -> Vec<U256> {
+    balances
+        .iter()
+        .zip(rates)
+        .map(|(balance, rate)| balance * rate / U256::from(PRECISION))
+        .collect()
+}
+
+//Calculates the StableSwap invariant D for the normalized balances `xp`
+pub fn get_d(xp: &[U256], amp: U256) -> Result<U256, SwapSimulationError> {
+    let n_coins = U256::from(xp.len());
+    let sum = xp.iter().fold(U256::zero(), |sum, x| sum + x);
+
+    if sum.is_zero() {
+        return Ok(U256::zero());
+    }
+
+    if xp.iter().any(|x| x.is_zero()) {
+        return Err(SwapSimulationError::InsufficientLiquidity);
+    }
+
+    //The invariant is undefined without amplification
+    if amp.is_zero() {
+        return Err(SwapSimulationError::InvariantDidNotConverge);
+    }
+
+    let ann = amp * n_coins;
+    let mut d = sum;
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut d_p = d;
+        for x in xp {
+            d_p = d_p * d / (x * n_coins);
+        }
+
+        let d_prev = d;
+        d = (ann * sum + d_p * n_coins) * d / ((ann - 1) * d + (n_coins + 1) * d_p);
+
+        if abs_diff(d, d_prev) <= U256::one() {
+            return Ok(d);
+        }
+    }
+
+    Err(SwapSimulationError::InvariantDidNotConverge)
+}
+
+//Calculates the new normalized balance of coin `j` given a new normalized balance `x` of coin `i`
+pub fn get_y(
+    i: usize,
+    j: usize,
+    x: U256,
+    xp: &[U256],
+    amp: U256,
+) -> Result<U256, SwapSimulationError> {
+    let n_coins = U256::from(xp.len());
+    let d = get_d(xp, amp)?;
+    let ann = amp * n_coins;
+
+    let mut c = d;
+    let mut sum = U256::zero();
+
+    for (k, balance) in xp.iter().enumerate() {
+        let x_k = if k == i {
+            x
+        } else if k != j {
+            *balance
+        } else {
+            continue;
+        };
+
+        sum += x_k;
+        c = c * d / (x_k * n_coins);
+    }
+
+    c = c * d / (ann * n_coins);
+    let b = sum + d / ann;
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        y = (y * y + c) / (U256::from(2) * y + b - d);
+
+        if abs_diff(y, y_prev) <= U256::one() {
+            return Ok(y);
+        }
+    }
+
+    Err(SwapSimulationError::InvariantDidNotConverge)
+}
+
+//Calculates the amount of coin `j` received for `dx` of coin `i`, mirroring `get_dy` on Curve pools
+pub fn get_dy(
+    i: usize,
+    j: usize,
+    dx: U256,
+    balances: &[U256],
+    rates: &[U256],
+    amp: U256,
+    fee: U256,
+) -> Result<U256, SwapSimulationError> {
+    let xp = xp(balances, rates);
+
+    let x = xp[i] + dx * rates[i] / U256::from(PRECISION);
+    let y = get_y(i, j, x, &xp, amp)?;
+
+    if xp[j] <= y + 1 {
+        return Err(SwapSimulationError::InsufficientLiquidity);
+    }
+
+    if fee > U256::from(FEE_DENOMINATOR) {
+        return Err(SwapSimulationError::InvalidFee(
+            u32::try_from(fee).unwrap_or(u32::MAX),
+        ));
+    }
+
+    let dy = (xp[j] - y - 1) * U256::from(PRECISION) / rates[j];
+    let dy_fee = dy * fee / FEE_DENOMINATOR;
+
+    Ok(dy - dy_fee)
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //A 3pool-like DAI/USDC/USDT pool with A = 2000 and a 0.01% fee. Expected values follow the 3pool contract math.
+    fn three_pool() -> (Vec<U256>, Vec<U256>, U256, U256) {
+        let balances = vec![
+            U256::from(165_000_000u128) * U256::exp10(18),
+            U256::from(170_000_000u128) * U256::exp10(6),
+            U256::from(60_000_000u128) * U256::exp10(6),
+        ];
+        let rates = vec![U256::exp10(18), U256::exp10(30), U256::exp10(30)];
+
+        (balances, rates, U256::from(2000), U256::from(1_000_000))
+    }
+
+    #[test]
+    fn test_get_d() {
+        let (balances, rates, amp, _) = three_pool();
+
+        let d = get_d(&xp(&balances, &rates), amp).unwrap();
+
+        assert_eq!(
+            d,
+            U256::from_dec_str("394976563406233494092914366").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_dy() {
+        let (balances, rates, amp, fee) = three_pool();
+
+        let dai_to_usdc = get_dy(
+            0,
+            1,
+            U256::from(1_000_000u128) * U256::exp10(18),
+            &balances,
+            &rates,
+            amp,
+            fee,
+        )
+        .unwrap();
+        assert_eq!(dai_to_usdc, U256::from(999_912_719_752u128));
+
+        let usdt_to_dai = get_dy(
+            2,
+            0,
+            U256::from(10_000_000u128) * U256::exp10(6),
+            &balances,
+            &rates,
+            amp,
+            fee,
+        )
+        .unwrap();
+        assert_eq!(
+            usdt_to_dai,
+            U256::from_dec_str("10006811032513965231742819").unwrap()
+        );
+
+        let usdc_to_usdt = get_dy(
+            1,
+            2,
+            U256::from(1000u128) * U256::exp10(6),
+            &balances,
+            &rates,
+            amp,
+            fee,
+        )
+        .unwrap();
+        assert_eq!(usdc_to_usdt, U256::from(998_938_835u128));
+    }
+
+    #[test]
+    fn test_get_d_empty_coin() {
+        let (mut balances, rates, amp, _) = three_pool();
+        balances[2] = U256::zero();
+
+        assert!(matches!(
+            get_d(&xp(&balances, &rates), amp),
+            Err(SwapSimulationError::InsufficientLiquidity)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        let (balances, rates, _, fee) = three_pool();
+
+        //A pool without amplification has no invariant
+        assert!(matches!(
+            get_dy(0, 1, U256::exp10(18), &balances, &rates, U256::zero(), fee),
+            Err(SwapSimulationError::InvariantDidNotConverge)
+        ));
+
+        //A fee above 100% can not be charged
+        assert!(matches!(
+            get_dy(
+                0,
+                1,
+                U256::exp10(18),
+                &balances,
+                &rates,
+                U256::from(2000),
+                U256::from(FEE_DENOMINATOR + 1)
+            ),
+            Err(SwapSimulationError::InvalidFee(_))
+        ));
+    }
+}
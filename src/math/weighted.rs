@@ -0,0 +1,119 @@
+use ethers::types::U256;
+
+use crate::error::SwapSimulationError;
+
+//Calculates the amount out for a Balancer weighted pool, following the formula of `WeightedMath._calcOutGivenIn`.
+//The fee must already be deducted from `amount_in`.
+//Unequal weights are approximated with f64 rather than Balancer's `LogExpMath.pow`, so the result can differ slightly
+//from the pool, with the relative error growing as the amount in becomes small compared to the balance in.
+pub fn get_amount_out(
+    balance_in: U256,
+    weight_in: U256,
+    balance_out: U256,
+    weight_out: U256,
+    amount_in: U256,
+) -> Result<U256, SwapSimulationError> {
+    if balance_in.is_zero() || balance_out.is_zero() {
+        return Err(SwapSimulationError::InsufficientLiquidity);
+    }
+
+    //Balancer reverts when the amount in is more than 30% of the balance in
+    if amount_in * 10 > balance_in * 3 {
+        return Err(SwapSimulationError::InsufficientLiquidity);
+    }
+
+    //Equal weights reduce to the constant product formula, which can be calculated exactly
+    if weight_in == weight_out {
+        return Ok(balance_out * amount_in / (balance_in + amount_in));
+    }
+
+    //amount_out = balance_out * (1 - (balance_in / (balance_in + amount_in)) ^ (weight_in / weight_out))
+    //The base is below one, so it is scaled to 18 decimals before converting to a float
+    let base = (balance_in * U256::exp10(18) / (balance_in + amount_in)).as_u128() as f64 / 1e18;
+    let exponent = weight_in.as_u128() as f64 / weight_out.as_u128() as f64;
+    let complement = 1.0 - base.powf(exponent);
+
+    Ok(balance_out * U256::from((complement * 1e18) as u128) / U256::exp10(18))
+}
+
+//Calculates the spot price of token out per token in, excluding fees
+pub fn get_spot_price(balance_in: f64, weight_in: f64, balance_out: f64, weight_out: f64) -> f64 {
+    (balance_out / weight_out) / (balance_in / weight_in)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u128 = 1_000_000_000_000_000_000;
+
+    //Asserts the amount out is within a relative tolerance of the exact value of the weighted math formula
+    fn assert_close(amount_out: U256, expected: f64) {
+        let relative_error = (amount_out.as_u128() as f64 - expected).abs() / expected;
+        assert!(
+            relative_error < 1e-9,
+            "{} differs from {} by {}",
+            amount_out,
+            expected,
+            relative_error
+        );
+    }
+
+    #[test]
+    fn test_get_amount_out_equal_weights() {
+        let amount_out = get_amount_out(
+            U256::from(1000 * ONE),
+            U256::from(ONE / 2),
+            U256::from(1000 * ONE),
+            U256::from(ONE / 2),
+            U256::from(10 * ONE),
+        )
+        .unwrap();
+
+        assert_eq!(amount_out, U256::from(9_900_990_099_009_900_990u128));
+    }
+
+    #[test]
+    fn test_get_amount_out_unequal_weights() {
+        //10 of an 18 decimal token into an 80/20 pool against a 6 decimal token
+        let amount_out = get_amount_out(
+            U256::from(10_000 * ONE),
+            U256::from(ONE * 8 / 10),
+            U256::from(40_000_000_000_000u128),
+            U256::from(ONE * 2 / 10),
+            U256::from(10 * ONE),
+        )
+        .unwrap();
+        assert_close(amount_out, 159_600_798_602.236_64);
+
+        //100,000 of a 6 decimal token into the 20% side of a 20/80 pool
+        let amount_out = get_amount_out(
+            U256::from(5_000_000_000_000u128),
+            U256::from(ONE * 2 / 10),
+            U256::from(2000 * ONE),
+            U256::from(ONE * 8 / 10),
+            U256::from(100_000_000_000u128),
+        )
+        .unwrap();
+        assert_close(amount_out, 9_876_845_040_313_281_111.6);
+    }
+
+    #[test]
+    fn test_get_amount_out_above_max_in_ratio() {
+        assert!(matches!(
+            get_amount_out(
+                U256::from(1000 * ONE),
+                U256::from(ONE / 2),
+                U256::from(1000 * ONE),
+                U256::from(ONE / 2),
+                U256::from(301 * ONE),
+            ),
+            Err(SwapSimulationError::InsufficientLiquidity)
+        ));
+    }
+
+    #[test]
+    fn test_get_spot_price() {
+        assert_eq!(get_spot_price(1000.0, 0.8, 1_000_000.0, 0.2), 4000.0);
+    }
+}
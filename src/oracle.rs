@@ -0,0 +1,349 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use ethers::types::H160;
+
+use crate::pool::{Pool, PoolKey, PoolVariant};
+
+//Price of a token in the oracle's quote token
+#[derive(Debug, Clone)]
+pub struct TokenPrice {
+    //Price of one token in the quote token
+    pub price: f64,
+    //Liquidity of the least liquid hop in the path, in the quote token
+    pub liquidity: f64,
+    //Tokens the price was routed through, starting with the token and ending with the quote token
+    pub path: Vec<H160>,
+}
+
+//Prices tokens in a quote token from a set of synced pools, without any calls to the node.
+//Pools are combined into a token graph and each token is priced through the path to the quote token
+//with the most liquid bottleneck. Pools pairing the same two tokens are aggregated, weighting each pool's
+//spot price by its liquidity.
+//The reserves of concentrated liquidity pools are virtual reserves, which can be far larger than the tokens the pool
+//holds, so these pools are only used when their held balances are provided, see `PriceOracle::new_with_held_balances`.
+#[derive(Debug, Clone)]
+pub struct PriceOracle {
+    quote_token: H160,
+    token_prices: HashMap<H160, TokenPrice>,
+}
+
+//Aggregated price of one token in units of another across every pool pairing them
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    token: H160,
+    //Price of `token` in units of the neighbouring token
+    price: f64,
+    //Balance of the neighbouring token held by the pools, adjusted for decimals
+    liquidity: f64,
+}
+
+//Token to visit in the widest path search, ordered by the liquidity of the path to it
+struct PathCandidate {
+    token: H160,
+    liquidity: f64,
+}
+
+impl PartialEq for PathCandidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.liquidity.total_cmp(&other.liquidity) == Ordering::Equal
+    }
+}
+
+impl Eq for PathCandidate {}
+
+impl PartialOrd for PathCandidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PathCandidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.liquidity.total_cmp(&other.liquidity)
+    }
+}
+
+impl PriceOracle {
+    //Builds the oracle from pools with synced reserves or balances, skipping concentrated liquidity pools
+    pub fn new(pools: &[Pool], quote_token: H160) -> PriceOracle {
+        PriceOracle::new_with_held_balances(pools, quote_token, &HashMap::new())
+    }
+
+    //Builds the oracle from pools with synced reserves or balances, using the held balances of each pool as its
+    //liquidity where provided, ie. from `get_pool_held_balances`. Concentrated liquidity pools without held balances are skipped.
+    pub fn new_with_held_balances(
+        pools: &[Pool],
+        quote_token: H160,
+        held_balances: &HashMap<PoolKey, Vec<(H160, u8, u128)>>,
+    ) -> PriceOracle {
+        let edges = get_edges(pools, held_balances);
+
+        let mut token_prices = HashMap::new();
+        token_prices.insert(
+            quote_token,
+            TokenPrice {
+                price: 1.0,
+                liquidity: f64::INFINITY,
+                path: vec![quote_token],
+            },
+        );
+
+        //Widest path search from the quote token, each token is settled once it has the most liquid path
+        let mut candidates = BinaryHeap::new();
+        candidates.push(PathCandidate {
+            token: quote_token,
+            liquidity: f64::INFINITY,
+        });
+
+        //Candidates are popped most liquid first, so a token is settled by its best path and later candidates are stale
+        let mut settled_tokens = HashSet::new();
+        while let Some(PathCandidate { token, .. }) = candidates.pop() {
+            if !settled_tokens.insert(token) {
+                continue;
+            }
+
+            let token_price = token_prices[&token].clone();
+
+            for edge in edges.get(&token).into_iter().flatten() {
+                if settled_tokens.contains(&edge.token) {
+                    continue;
+                }
+
+                //The edge liquidity is held in `token`, so it is converted to the quote token with the token's price
+                let path_liquidity = token_price
+                    .liquidity
+                    .min(edge.liquidity * token_price.price);
+
+                let is_better = match token_prices.get(&edge.token) {
+                    Some(existing) => path_liquidity > existing.liquidity,
+                    None => true,
+                };
+
+                if is_better {
+                    let mut path = vec![edge.token];
+                    path.extend(token_price.path.iter());
+
+                    token_prices.insert(
+                        edge.token,
+                        TokenPrice {
+                            price: edge.price * token_price.price,
+                            liquidity: path_liquidity,
+                            path,
+                        },
+                    );
+
+                    candidates.push(PathCandidate {
+                        token: edge.token,
+                        liquidity: path_liquidity,
+                    });
+                }
+            }
+        }
+
+        PriceOracle {
+            quote_token,
+            token_prices,
+        }
+    }
+
+    pub fn quote_token(&self) -> H160 {
+        self.quote_token
+    }
+
+    //Gets the price of one token in the quote token, None if the token is not connected to the quote token
+    pub fn get_price(&self, token: H160) -> Option<f64> {
+        self.token_prices
+            .get(&token)
+            .map(|token_price| token_price.price)
+    }
+
+    //Gets the price of a token along with the path and liquidity it was priced through
+    pub fn get_token_price(&self, token: H160) -> Option<&TokenPrice> {
+        self.token_prices.get(&token)
+    }
+
+    //Gets the price of token_a in units of token_b
+    pub fn get_relative_price(&self, token_a: H160, token_b: H160) -> Option<f64> {
+        let price_a = self.get_price(token_a)?;
+        let price_b = self.get_price(token_b)?;
+
+        if price_b == 0.0 {
+            return None;
+        }
+
+        Some(price_a / price_b)
+    }
+
+    pub fn token_prices(&self) -> &HashMap<H160, TokenPrice> {
+        &self.token_prices
+    }
+}
+
+//Builds the token graph, mapping each token to the aggregated prices of its neighbours in units of the token
+fn get_edges(
+    pools: &[Pool],
+    held_balances: &HashMap<PoolKey, Vec<(H160, u8, u128)>>,
+) -> HashMap<H160, Vec<Edge>> {
+    //Spot price and liquidity of every pool pairing two tokens, keyed by (token, token the price is in)
+    let mut pool_prices: HashMap<(H160, H160), Vec<(f64, f64)>> = HashMap::new();
+
+    for pool in pools {
+        let token_balances = match held_balances.get(&pool.get_pool_key()) {
+            Some(token_balances) => token_balances.clone(),
+            None => match pool.pool_variant {
+                PoolVariant::UniswapV3 | PoolVariant::UniswapV4 | PoolVariant::Algebra => continue,
+                _ => pool.get_token_balances(),
+            },
+        };
+
+        for (token, _, balance) in token_balances.iter() {
+            for (quote_token, quote_decimals, quote_balance) in token_balances.iter() {
+                if token == quote_token || *balance == 0 || *quote_balance == 0 {
+                    continue;
+                }
+
+                //Pools that can not be priced from their state are skipped
+                let price = match pool.get_spot_price(*token, *quote_token) {
+                    Ok(price) if price.is_finite() && price > 0.0 => price,
+                    _ => continue,
+                };
+
+                let liquidity = *quote_balance as f64 / 10f64.powf((*quote_decimals).into());
+
+                pool_prices
+                    .entry((*token, *quote_token))
+                    .or_default()
+                    .push((price, liquidity));
+            }
+        }
+    }
+
+    let mut edges: HashMap<H160, Vec<Edge>> = HashMap::new();
+    for ((token, quote_token), prices) in pool_prices {
+        let liquidity: f64 = prices.iter().map(|(_, liquidity)| liquidity).sum();
+
+        if liquidity == 0.0 {
+            continue;
+        }
+
+        let price = prices
+            .iter()
+            .map(|(price, liquidity)| price * liquidity)
+            .sum::<f64>()
+            / liquidity;
+
+        edges.entry(quote_token).or_default().push(Edge {
+            token,
+            price,
+            liquidity,
+        });
+    }
+
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u128 = 1_000_000_000_000_000_000;
+
+    fn token(byte: u8) -> H160 {
+        H160::repeat_byte(byte)
+    }
+
+    fn pool(
+        address: u8,
+        token_a: H160,
+        token_b: H160,
+        reserve_a: u128,
+        reserve_b: u128,
+        pool_variant: PoolVariant,
+    ) -> Pool {
+        Pool::new(
+            token(address),
+            token_a,
+            18,
+            token_b,
+            18,
+            true,
+            reserve_a * ONE,
+            reserve_b * ONE,
+            3000,
+            pool_variant,
+        )
+    }
+
+    #[test]
+    fn test_widest_path() {
+        let (quote, a, b) = (token(1), token(2), token(3));
+
+        let pools = vec![
+            //A thin direct pool pricing A at 0.02
+            pool(10, a, quote, 50, 1, PoolVariant::UniswapV2),
+            //A deep route through B pricing A at 0.01
+            pool(11, a, b, 100_000, 1000, PoolVariant::UniswapV2),
+            pool(12, b, quote, 1000, 1000, PoolVariant::UniswapV2),
+        ];
+
+        let oracle = PriceOracle::new(&pools, quote);
+        let token_price = oracle.get_token_price(a).unwrap();
+
+        assert_eq!(token_price.path, vec![a, b, quote]);
+        assert!((token_price.price - 0.01).abs() < 1e-12);
+        assert!((token_price.liquidity - 1000.0).abs() < 1e-9);
+
+        assert_eq!(oracle.get_price(quote), Some(1.0));
+        assert!((oracle.get_relative_price(b, a).unwrap() - 100.0).abs() < 1e-9);
+        assert!(oracle.get_price(token(4)).is_none());
+    }
+
+    #[test]
+    fn test_aggregate_parallel_pools() {
+        let (quote, a) = (token(1), token(2));
+
+        let pools = vec![
+            pool(10, a, quote, 1000, 1000, PoolVariant::UniswapV2),
+            pool(11, a, quote, 1000, 3000, PoolVariant::UniswapV2),
+        ];
+
+        let token_price = PriceOracle::new(&pools, quote)
+            .get_token_price(a)
+            .unwrap()
+            .clone();
+
+        //Prices of 1 and 3 weighted by the 1000 and 3000 quote tokens held
+        assert!((token_price.price - 2.5).abs() < 1e-12);
+        assert!((token_price.liquidity - 4000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_concentrated_liquidity_held_balances() {
+        let (quote, a) = (token(1), token(2));
+
+        //Virtual reserves of a concentrated liquidity pool, far larger than the tokens held
+        let pools = vec![pool(
+            10,
+            a,
+            quote,
+            1_000_000,
+            2_000_000,
+            PoolVariant::UniswapV3,
+        )];
+
+        assert!(PriceOracle::new(&pools, quote).get_price(a).is_none());
+
+        let held_balances = HashMap::from([(
+            pools[0].get_pool_key(),
+            vec![(a, 18, 10 * ONE), (quote, 18, 20 * ONE)],
+        )]);
+        let oracle = PriceOracle::new_with_held_balances(&pools, quote, &held_balances);
+        let token_price = oracle.get_token_price(a).unwrap();
+
+        assert!((token_price.price - 2.0).abs() < 1e-12);
+        assert!((token_price.liquidity - 20.0).abs() < 1e-9);
+    }
+}
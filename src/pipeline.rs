@@ -0,0 +1,773 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use ethers::{
+    providers::{JsonRpcClient, Provider},
+    types::{H160, H256},
+};
+use futures::future::BoxFuture;
+
+use crate::{
+    dex::Dex,
+    error::PairSyncError,
+    filter::{
+        filter_fee_tokens_with_context, filter_honeypots_with_context,
+        filter_pools_below_value_threshold_with_context, filter_unverified_pools_with_context,
+        get_disallowed_token, AllowlistMode,
+    },
+    pool::{Pool, PoolKey},
+    report::{ExclusionReason, FilterReport},
+    throttle::RequestThrottle,
+    tvl::QuoteToken,
+};
+
+//State shared by every stage of a filter pipeline, so stages use one request throttle and one token price cache
+pub struct FilterContext<P: JsonRpcClient> {
+    pub provider: Arc<Provider<P>>,
+    pub(crate) request_throttle: Arc<Mutex<RequestThrottle>>,
+    //Price of each token in a base token, ie. weth, keyed by (token, base token) and found by the threshold filters
+    pub token_prices: Arc<Mutex<HashMap<(H160, H160), f64>>>,
+    //Pools removed by each filter and the reason they were removed, if reporting is enabled
+    pub report: Option<Arc<Mutex<FilterReport>>>,
+    //Storage slot holding the round trip simulator's balance of each quote token, found once per token by the honeypot filter
+    pub(crate) balance_slots: Arc<Mutex<HashMap<H160, BalanceSlotCell>>>,
+}
+
+pub(crate) type BalanceSlotCell = Arc<tokio::sync::OnceCell<Option<H256>>>;
+
+//Clones share the provider, throttle, price cache, report and balance slots
+impl<P: JsonRpcClient> Clone for FilterContext<P> {
+    fn clone(&self) -> Self {
+        FilterContext {
+            provider: self.provider.clone(),
+            request_throttle: self.request_throttle.clone(),
+            token_prices: self.token_prices.clone(),
+            report: self.report.clone(),
+            balance_slots: self.balance_slots.clone(),
+        }
+    }
+}
+
+impl<P: JsonRpcClient> FilterContext<P> {
+    pub fn new(provider: Arc<Provider<P>>, requests_per_second_limit: usize) -> FilterContext<P> {
+        FilterContext {
+            provider,
+            request_throttle: Arc::new(Mutex::new(RequestThrottle::new(requests_per_second_limit))),
+            token_prices: Arc::new(Mutex::new(HashMap::new())),
+            report: None,
+            balance_slots: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    //Enables the filter report. Pools that fail a call to the node are then removed and reported
+    //instead of the error being returned.
+    pub fn with_report(mut self) -> FilterContext<P> {
+        self.report = Some(Arc::new(Mutex::new(FilterReport::new())));
+        self
+    }
+
+    //Takes the report, leaving an empty report in its place
+    pub fn take_report(&self) -> FilterReport {
+        match &self.report {
+            Some(report) => std::mem::take(&mut *report.lock().unwrap()),
+            None => FilterReport::new(),
+        }
+    }
+
+    pub(crate) fn exclude_pool(&self, pool: Pool, reason: ExclusionReason) {
+        if let Some(report) = &self.report {
+            report.lock().unwrap().exclude_pool(pool, reason);
+        }
+    }
+
+    //Handles an error from checking a single pool. Pools without a price route or separate balances are always removed,
+    //other errors are reported if reporting is enabled and returned otherwise.
+    pub(crate) fn exclude_pool_with_error(
+        &self,
+        pool: Pool,
+        pair_sync_error: PairSyncError<P>,
+    ) -> Result<(), PairSyncError<P>> {
+        match pair_sync_error {
+            PairSyncError::PairDoesNotExistInDexes(token, _) => {
+                self.exclude_pool(pool, ExclusionReason::NoPriceRoute(token));
+                Ok(())
+            }
+            PairSyncError::UnknownPoolBalances(_) => {
+                self.exclude_pool(pool, ExclusionReason::UnknownBalances);
+                Ok(())
+            }
+            _ if self.report.is_some() => {
+                self.exclude_pool(
+                    pool,
+                    ExclusionReason::RequestError(format!("{:?}", pair_sync_error)),
+                );
+                Ok(())
+            }
+            _ => Err(pair_sync_error),
+        }
+    }
+
+    //Creates a context sharing the throttle and price cache, with a separate report.
+    //Used by combinators, where a pool removed by an inner filter may still be kept.
+    fn with_scratch_report(&self) -> FilterContext<P> {
+        FilterContext {
+            report: self
+                .report
+                .as_ref()
+                .map(|_| Arc::new(Mutex::new(FilterReport::new()))),
+            ..self.clone()
+        }
+    }
+}
+
+//A filter that decides on each pool on its own without any calls to the node
+pub trait SyncPoolFilter: Send + Sync {
+    //Returns true if the pool should be kept
+    fn keep_pool(&self, pool: &Pool) -> bool;
+
+    //Reason recorded in the filter report when the pool is removed
+    fn exclusion_reason(&self, _pool: &Pool) -> ExclusionReason {
+        ExclusionReason::CustomFilter
+    }
+}
+
+//Closures can be used as custom filters, ie. `|pool: &Pool| pool.reserve_0 > 0`
+impl<F: Fn(&Pool) -> bool + Send + Sync> SyncPoolFilter for F {
+    fn keep_pool(&self, pool: &Pool) -> bool {
+        self(pool)
+    }
+}
+
+//A filter that can make calls to the node, taking the pools and returning the pools that are kept
+pub trait PoolFilter<P: JsonRpcClient>: Send + Sync {
+    fn filter<'a>(
+        &'a self,
+        pools: Vec<Pool>,
+        context: &'a FilterContext<P>,
+    ) -> BoxFuture<'a, Result<Vec<Pool>, PairSyncError<P>>>;
+}
+
+impl<P: JsonRpcClient, T: SyncPoolFilter> PoolFilter<P> for T {
+    fn filter<'a>(
+        &'a self,
+        pools: Vec<Pool>,
+        context: &'a FilterContext<P>,
+    ) -> BoxFuture<'a, Result<Vec<Pool>, PairSyncError<P>>> {
+        Box::pin(async move {
+            let mut kept_pools = vec![];
+
+            for pool in pools {
+                if self.keep_pool(&pool) {
+                    kept_pools.push(pool);
+                } else {
+                    let reason = self.exclusion_reason(&pool);
+                    context.exclude_pool(pool, reason);
+                }
+            }
+
+            Ok(kept_pools)
+        })
+    }
+}
+
+//Keeps pools that are kept by both filters
+pub struct And<A, B>(pub A, pub B);
+
+//Keeps pools that are kept by either filter. The second filter only sees the pools removed by the first.
+pub struct Or<A, B>(pub A, pub B);
+
+//Keeps pools that are removed by the filter
+pub struct Not<A>(pub A);
+
+impl<P: JsonRpcClient, A: PoolFilter<P>, B: PoolFilter<P>> PoolFilter<P> for And<A, B> {
+    fn filter<'a>(
+        &'a self,
+        pools: Vec<Pool>,
+        context: &'a FilterContext<P>,
+    ) -> BoxFuture<'a, Result<Vec<Pool>, PairSyncError<P>>> {
+        Box::pin(async move {
+            let pools = self.0.filter(pools, context).await?;
+            self.1.filter(pools, context).await
+        })
+    }
+}
+
+impl<P: JsonRpcClient, A: PoolFilter<P>, B: PoolFilter<P>> PoolFilter<P> for Or<A, B> {
+    fn filter<'a>(
+        &'a self,
+        pools: Vec<Pool>,
+        context: &'a FilterContext<P>,
+    ) -> BoxFuture<'a, Result<Vec<Pool>, PairSyncError<P>>> {
+        Box::pin(async move {
+            //Pools removed by the first filter can still be kept by the second, so only the second filter's reasons are reported
+            let scratch_context = context.with_scratch_report();
+            let mut kept_pools =
+                get_pool_keys(&self.0.filter(pools.clone(), &scratch_context).await?);
+
+            let removed_pools = pools
+                .iter()
+                .filter(|pool| !kept_pools.contains(&pool.get_pool_key()))
+                .cloned()
+                .collect();
+            kept_pools.extend(get_pool_keys(&self.1.filter(removed_pools, context).await?));
+
+            //Keep the pools in their original order
+            Ok(pools
+                .into_iter()
+                .filter(|pool| kept_pools.contains(&pool.get_pool_key()))
+                .collect())
+        })
+    }
+}
+
+impl<P: JsonRpcClient, A: PoolFilter<P>> PoolFilter<P> for Not<A> {
+    fn filter<'a>(
+        &'a self,
+        pools: Vec<Pool>,
+        context: &'a FilterContext<P>,
+    ) -> BoxFuture<'a, Result<Vec<Pool>, PairSyncError<P>>> {
+        Box::pin(async move {
+            //Pools removed by the inner filter are kept, so its reasons are discarded
+            let scratch_context = context.with_scratch_report();
+            let kept_pools = get_pool_keys(&self.0.filter(pools.clone(), &scratch_context).await?);
+
+            let mut removed_pools = vec![];
+            for pool in pools {
+                if kept_pools.contains(&pool.get_pool_key()) {
+                    context.exclude_pool(pool, ExclusionReason::NegatedFilter);
+                } else {
+                    removed_pools.push(pool);
+                }
+            }
+
+            Ok(removed_pools)
+        })
+    }
+}
+
+fn get_pool_keys(pools: &[Pool]) -> HashSet<PoolKey> {
+    pools.iter().map(Pool::get_pool_key).collect()
+}
+
+//Removes pools where the pool or any of the pool's tokens is blacklisted
+#[derive(Debug, Clone, Default)]
+pub struct BlacklistFilter {
+    pub tokens: HashSet<H160>,
+    pub pools: HashSet<PoolKey>,
+}
+
+impl BlacklistFilter {
+    //Blacklists the tokens and the pools at the addresses. UniswapV4 pools are blacklisted by pool id, see `with_pool_ids`.
+    pub fn new(tokens: Vec<H160>, pools: Vec<H160>) -> BlacklistFilter {
+        BlacklistFilter {
+            tokens: tokens.into_iter().collect(),
+            pools: pools.into_iter().map(PoolKey::Address).collect(),
+        }
+    }
+
+    //Blacklists the UniswapV4 pools with the pool ids
+    pub fn with_pool_ids(mut self, pool_ids: Vec<H256>) -> BlacklistFilter {
+        self.pools.extend(pool_ids.into_iter().map(PoolKey::PoolId));
+        self
+    }
+}
+
+impl SyncPoolFilter for BlacklistFilter {
+    fn keep_pool(&self, pool: &Pool) -> bool {
+        !self.pools.contains(&pool.get_pool_key())
+            && !self.tokens.contains(&pool.token_a)
+            && !self.tokens.contains(&pool.token_b)
+            && !pool.tokens.iter().any(|token| self.tokens.contains(token))
+    }
+
+    fn exclusion_reason(&self, pool: &Pool) -> ExclusionReason {
+        if self.pools.contains(&pool.get_pool_key()) {
+            return ExclusionReason::BlacklistedPool(pool.get_pool_key());
+        }
+
+        match [pool.token_a, pool.token_b]
+            .iter()
+            .chain(pool.tokens.iter())
+            .find(|token| self.tokens.contains(token))
+        {
+            Some(token) => ExclusionReason::BlacklistedToken(*token),
+            None => ExclusionReason::CustomFilter,
+        }
+    }
+}
+
+//Removes pools whose tokens are not allowlisted, see `filter::filter_pools_outside_allowlist`
+#[derive(Debug, Clone)]
+pub struct AllowlistFilter {
+    pub tokens: HashSet<H160>,
+    pub allowlist_mode: AllowlistMode,
+}
+
+impl AllowlistFilter {
+    pub fn new(tokens: Vec<H160>, allowlist_mode: AllowlistMode) -> AllowlistFilter {
+        AllowlistFilter {
+            tokens: tokens.into_iter().collect(),
+            allowlist_mode,
+        }
+    }
+}
+
+impl SyncPoolFilter for AllowlistFilter {
+    fn keep_pool(&self, pool: &Pool) -> bool {
+        get_disallowed_token(pool, &self.tokens, self.allowlist_mode).is_none()
+    }
+
+    fn exclusion_reason(&self, pool: &Pool) -> ExclusionReason {
+        match get_disallowed_token(pool, &self.tokens, self.allowlist_mode) {
+            Some(token) => ExclusionReason::NotAllowlisted(token),
+            None => ExclusionReason::CustomFilter,
+        }
+    }
+}
+
+//Removes pools below a value in the quote token, see `filter::filter_pools_below_value_threshold`
+#[derive(Debug, Clone)]
+pub struct ValueThresholdFilter {
+    pub dexes: Vec<Dex>,
+    pub quote_token: QuoteToken,
+    pub threshold: f64,
+}
+
+impl<P: 'static + JsonRpcClient> PoolFilter<P> for ValueThresholdFilter {
+    fn filter<'a>(
+        &'a self,
+        pools: Vec<Pool>,
+        context: &'a FilterContext<P>,
+    ) -> BoxFuture<'a, Result<Vec<Pool>, PairSyncError<P>>> {
+        Box::pin(filter_pools_below_value_threshold_with_context(
+            pools,
+            self.dexes.clone(),
+            &self.quote_token,
+            self.threshold,
+            context,
+        ))
+    }
+}
+
+//Removes pools below a USD value, see `filter::filter_pools_below_usd_threshold`
+#[derive(Debug, Clone)]
+pub struct UsdThresholdFilter {
+    pub dexes: Vec<Dex>,
+    pub usd_weth_pool: Pool,
+    pub weth_address: H160,
+    pub usd_threshold: f64,
+}
+
+impl<P: 'static + JsonRpcClient> PoolFilter<P> for UsdThresholdFilter {
+    fn filter<'a>(
+        &'a self,
+        pools: Vec<Pool>,
+        context: &'a FilterContext<P>,
+    ) -> BoxFuture<'a, Result<Vec<Pool>, PairSyncError<P>>> {
+        Box::pin(async move {
+            let quote_token =
+                QuoteToken::new(self.weth_address).with_reference_pool(self.usd_weth_pool.clone());
+
+            filter_pools_below_value_threshold_with_context(
+                pools,
+                self.dexes.clone(),
+                &quote_token,
+                self.usd_threshold,
+                context,
+            )
+            .await
+        })
+    }
+}
+
+//Removes pools below a WETH value, see `filter::filter_pools_below_weth_threshold`
+#[derive(Debug, Clone)]
+pub struct WethThresholdFilter {
+    pub dexes: Vec<Dex>,
+    pub weth_address: H160,
+    pub weth_threshold: f64,
+}
+
+impl<P: 'static + JsonRpcClient> PoolFilter<P> for WethThresholdFilter {
+    fn filter<'a>(
+        &'a self,
+        pools: Vec<Pool>,
+        context: &'a FilterContext<P>,
+    ) -> BoxFuture<'a, Result<Vec<Pool>, PairSyncError<P>>> {
+        Box::pin(async move {
+            filter_pools_below_value_threshold_with_context(
+                pools,
+                self.dexes.clone(),
+                &QuoteToken::new(self.weth_address),
+                self.weth_threshold,
+                context,
+            )
+            .await
+        })
+    }
+}
+
+//Removes pools with fee on transfer tokens, see `filter::filter_fee_tokens`
+#[derive(Debug, Clone, Copy)]
+pub struct FeeTokenFilter {
+    pub tax_threshold: f64,
+}
+
+impl<P: 'static + JsonRpcClient> PoolFilter<P> for FeeTokenFilter {
+    fn filter<'a>(
+        &'a self,
+        pools: Vec<Pool>,
+        context: &'a FilterContext<P>,
+    ) -> BoxFuture<'a, Result<Vec<Pool>, PairSyncError<P>>> {
+        Box::pin(async move {
+            let (pools, _) =
+                filter_fee_tokens_with_context(pools, self.tax_threshold, context).await?;
+            Ok(pools)
+        })
+    }
+}
+
+//Removes pools with tokens that can not be sold back, see `filter::filter_honeypots`
+#[derive(Debug, Clone, Copy)]
+pub struct HoneypotFilter {
+    pub max_loss: f64,
+}
+
+impl<P: 'static + JsonRpcClient> PoolFilter<P> for HoneypotFilter {
+    fn filter<'a>(
+        &'a self,
+        pools: Vec<Pool>,
+        context: &'a FilterContext<P>,
+    ) -> BoxFuture<'a, Result<Vec<Pool>, PairSyncError<P>>> {
+        Box::pin(filter_honeypots_with_context(pools, self.max_loss, context))
+    }
+}
+
+//Removes pools that were not deployed by one of the dexes, see `filter::filter_unverified_pools`
+#[derive(Debug, Clone)]
+pub struct VerifiedPoolFilter {
+    pub dexes: Vec<Dex>,
+}
+
+impl<P: 'static + JsonRpcClient> PoolFilter<P> for VerifiedPoolFilter {
+    fn filter<'a>(
+        &'a self,
+        pools: Vec<Pool>,
+        context: &'a FilterContext<P>,
+    ) -> BoxFuture<'a, Result<Vec<Pool>, PairSyncError<P>>> {
+        Box::pin(filter_unverified_pools_with_context(
+            pools,
+            self.dexes.clone(),
+            context,
+        ))
+    }
+}
+
+//Chains filters that are applied in order, sharing one request throttle and token price cache
+pub struct FilterPipeline<P: JsonRpcClient> {
+    stages: Vec<Box<dyn PoolFilter<P>>>,
+}
+
+impl<P: JsonRpcClient> Default for FilterPipeline<P> {
+    fn default() -> Self {
+        FilterPipeline::new()
+    }
+}
+
+impl<P: JsonRpcClient> FilterPipeline<P> {
+    pub fn new() -> FilterPipeline<P> {
+        FilterPipeline { stages: vec![] }
+    }
+
+    //Adds a filter to the end of the pipeline
+    pub fn add_filter<F: PoolFilter<P> + 'static>(mut self, filter: F) -> FilterPipeline<P> {
+        self.stages.push(Box::new(filter));
+        self
+    }
+
+    pub async fn run(
+        &self,
+        pools: Vec<Pool>,
+        provider: Arc<Provider<P>>,
+    ) -> Result<Vec<Pool>, PairSyncError<P>> {
+        self.run_with_throttle(pools, provider, 0).await
+    }
+
+    pub async fn run_with_throttle(
+        &self,
+        pools: Vec<Pool>,
+        provider: Arc<Provider<P>>,
+        requests_per_second_limit: usize,
+    ) -> Result<Vec<Pool>, PairSyncError<P>> {
+        let context = FilterContext::new(provider, requests_per_second_limit);
+        self.run_with_context(pools, &context).await
+    }
+
+    //Runs the pipeline, also returning the pools removed by each filter and the reason they were removed.
+    //Pools that fail a call to the node are removed and reported instead of failing the pipeline.
+    pub async fn run_with_report(
+        &self,
+        pools: Vec<Pool>,
+        provider: Arc<Provider<P>>,
+        requests_per_second_limit: usize,
+    ) -> Result<(Vec<Pool>, FilterReport), PairSyncError<P>> {
+        let context = FilterContext::new(provider, requests_per_second_limit).with_report();
+        let pools = self.run_with_context(pools, &context).await?;
+
+        Ok((pools, context.take_report()))
+    }
+
+    //Runs the pipeline with an existing context, ie. to reuse token prices found by a previous run
+    pub async fn run_with_context(
+        &self,
+        mut pools: Vec<Pool>,
+        context: &FilterContext<P>,
+    ) -> Result<Vec<Pool>, PairSyncError<P>> {
+        for stage in self.stages.iter() {
+            pools = stage.filter(pools, context).await?;
+        }
+
+        Ok(pools)
+    }
+}
+
+impl<P: JsonRpcClient> PoolFilter<P> for FilterPipeline<P> {
+    fn filter<'a>(
+        &'a self,
+        pools: Vec<Pool>,
+        context: &'a FilterContext<P>,
+    ) -> BoxFuture<'a, Result<Vec<Pool>, PairSyncError<P>>> {
+        Box::pin(self.run_with_context(pools, context))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::providers::Http;
+
+    use super::*;
+    use crate::pool::PoolVariant;
+
+    fn token(byte: u8) -> H160 {
+        H160::repeat_byte(byte)
+    }
+
+    fn pool(address: u8, token_a: u8, token_b: u8) -> Pool {
+        Pool::new(
+            token(address),
+            token(token_a),
+            18,
+            token(token_b),
+            18,
+            true,
+            0,
+            0,
+            300,
+            PoolVariant::UniswapV2,
+        )
+    }
+
+    //Curve pool holding every token, with token_a and token_b unset
+    fn curve_pool(address: u8, tokens: &[u8]) -> Pool {
+        let mut pool = Pool::new_curve_pool(token(address));
+        pool.tokens = tokens.iter().map(|byte| token(*byte)).collect();
+        pool
+    }
+
+    //Sync filters make no calls, so the provider is never connected to
+    fn reporting_context() -> FilterContext<Http> {
+        let provider = Provider::<Http>::try_from("http://localhost:8545").unwrap();
+        FilterContext::new(Arc::new(provider), 0).with_report()
+    }
+
+    fn addresses(pools: &[Pool]) -> Vec<H160> {
+        pools.iter().map(|pool| pool.address).collect()
+    }
+
+    fn exclusion_reasons(report: &FilterReport, address: u8) -> Vec<ExclusionReason> {
+        report
+            .get_exclusion_reasons(token(address))
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_blacklist_filter() {
+        let context = reporting_context();
+        let pools = vec![
+            pool(10, 1, 2),
+            pool(11, 3, 4),
+            pool(12, 3, 4),
+            curve_pool(13, &[3, 4, 5]),
+        ];
+
+        let blacklist = BlacklistFilter::new(vec![token(2), token(5)], vec![token(12)]);
+        let kept_pools = blacklist.filter(pools, &context).await.unwrap();
+        assert_eq!(addresses(&kept_pools), vec![token(11)]);
+
+        let report = context.take_report();
+        assert_eq!(report.len(), 3);
+        assert!(matches!(
+            exclusion_reasons(&report, 10)[..],
+            [ExclusionReason::BlacklistedToken(blacklisted)] if blacklisted == token(2)
+        ));
+        assert!(matches!(
+            exclusion_reasons(&report, 12)[..],
+            [ExclusionReason::BlacklistedPool(PoolKey::Address(blacklisted))] if blacklisted == token(12)
+        ));
+        //Tokens of multi token pools beyond token_a and token_b are checked
+        assert!(matches!(
+            exclusion_reasons(&report, 13)[..],
+            [ExclusionReason::BlacklistedToken(blacklisted)] if blacklisted == token(5)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_allowlist_filter() {
+        let pools = vec![
+            pool(10, 1, 2),
+            pool(11, 1, 3),
+            pool(12, 3, 4),
+            curve_pool(13, &[1, 2, 5]),
+        ];
+
+        let context = reporting_context();
+        let allowlist = AllowlistFilter::new(vec![token(1), token(2)], AllowlistMode::AnyToken);
+        let kept_pools = allowlist.filter(pools.clone(), &context).await.unwrap();
+        assert_eq!(
+            addresses(&kept_pools),
+            vec![token(10), token(11), token(13)]
+        );
+
+        //Pools without an allowlisted token report their first token
+        let report = context.take_report();
+        assert_eq!(report.len(), 1);
+        assert!(matches!(
+            exclusion_reasons(&report, 12)[..],
+            [ExclusionReason::NotAllowlisted(disallowed)] if disallowed == token(3)
+        ));
+
+        let context = reporting_context();
+        let allowlist = AllowlistFilter::new(vec![token(1), token(2)], AllowlistMode::AllTokens);
+        let kept_pools = allowlist.filter(pools, &context).await.unwrap();
+        assert_eq!(addresses(&kept_pools), vec![token(10)]);
+
+        //Pools report their first token that is not allowlisted
+        let report = context.take_report();
+        assert_eq!(report.len(), 3);
+        for (address, disallowed_token) in [(11, 3), (12, 3), (13, 5)] {
+            assert!(matches!(
+                exclusion_reasons(&report, address)[..],
+                [ExclusionReason::NotAllowlisted(disallowed)] if disallowed == token(disallowed_token)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_and_filter() {
+        let context = reporting_context();
+        let pools = vec![pool(10, 1, 2), pool(11, 1, 3), pool(12, 3, 4)];
+
+        let filter = And(
+            BlacklistFilter::new(vec![token(2)], vec![]),
+            AllowlistFilter::new(vec![token(1)], AllowlistMode::AnyToken),
+        );
+        let kept_pools = filter.filter(pools, &context).await.unwrap();
+        assert_eq!(addresses(&kept_pools), vec![token(11)]);
+
+        //Each pool is reported by the filter that removed it
+        let report = context.take_report();
+        assert_eq!(report.len(), 2);
+        assert!(matches!(
+            exclusion_reasons(&report, 10)[..],
+            [ExclusionReason::BlacklistedToken(_)]
+        ));
+        assert!(matches!(
+            exclusion_reasons(&report, 12)[..],
+            [ExclusionReason::NotAllowlisted(_)]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_or_filter() {
+        let context = reporting_context();
+        let pools = vec![pool(10, 1, 2), pool(11, 3, 4), pool(12, 5, 6)];
+
+        let filter = Or(
+            AllowlistFilter::new(vec![token(1)], AllowlistMode::AnyToken),
+            AllowlistFilter::new(vec![token(3)], AllowlistMode::AnyToken),
+        );
+        let kept_pools = filter.filter(pools, &context).await.unwrap();
+        assert_eq!(addresses(&kept_pools), vec![token(10), token(11)]);
+
+        //Pools removed by the first filter and kept by the second are not reported
+        let report = context.take_report();
+        assert_eq!(report.len(), 1);
+        assert!(matches!(
+            exclusion_reasons(&report, 12)[..],
+            [ExclusionReason::NotAllowlisted(disallowed)] if disallowed == token(5)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_not_filter() {
+        let context = reporting_context();
+        let pools = vec![pool(10, 1, 2), pool(11, 3, 4), pool(12, 1, 5)];
+
+        let filter = Not(BlacklistFilter::new(vec![token(1)], vec![]));
+        let kept_pools = filter.filter(pools, &context).await.unwrap();
+        assert_eq!(addresses(&kept_pools), vec![token(10), token(12)]);
+
+        //Pools kept by the inner filter are reported as negated, the inner filter's reasons are discarded
+        let report = context.take_report();
+        assert_eq!(report.len(), 1);
+        assert!(matches!(
+            exclusion_reasons(&report, 11)[..],
+            [ExclusionReason::NegatedFilter]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_composed_filters() {
+        let context = reporting_context();
+        let pools = vec![
+            pool(10, 1, 2),
+            pool(11, 1, 3),
+            pool(12, 4, 5),
+            pool(13, 6, 7),
+        ];
+
+        //Pools paired with token 1 or token 4, except those holding token 3
+        let filter = FilterPipeline::new()
+            .add_filter(Or(
+                AllowlistFilter::new(vec![token(1)], AllowlistMode::AnyToken),
+                AllowlistFilter::new(vec![token(4)], AllowlistMode::AnyToken),
+            ))
+            .add_filter(Not(Not(BlacklistFilter::new(vec![token(3)], vec![]))));
+        let kept_pools = filter.run_with_context(pools, &context).await.unwrap();
+        assert_eq!(addresses(&kept_pools), vec![token(10), token(12)]);
+
+        let report = context.take_report();
+        assert_eq!(report.len(), 2);
+        assert!(matches!(
+            exclusion_reasons(&report, 11)[..],
+            [ExclusionReason::NegatedFilter]
+        ));
+        assert!(matches!(
+            exclusion_reasons(&report, 13)[..],
+            [ExclusionReason::NotAllowlisted(_)]
+        ));
+
+        //Filters without a report do not record reasons
+        let context = FilterContext::new(context.provider.clone(), 0);
+        let kept_pools = Not(BlacklistFilter::new(vec![token(1)], vec![]))
+            .filter(vec![pool(10, 1, 2), pool(11, 3, 4)], &context)
+            .await
+            .unwrap();
+        assert_eq!(addresses(&kept_pools), vec![token(10)]);
+        assert!(context.take_report().is_empty());
+    }
+}
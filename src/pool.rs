@@ -1,12 +1,33 @@
-use std::{ops::Shr, str::FromStr, sync::Arc};
+use std::{str::FromStr, sync::Arc};
 
-use crate::{abi, error::PairSyncError};
+use crate::{
+    abi,
+    dex::Dex,
+    error::{PairSyncError, SwapSimulationError},
+    math::{concentrated_liquidity, constant_product, solidly, stable_swap, u256_to_f64, weighted},
+    registry,
+};
 use ethers::{
-    providers::{JsonRpcClient, Provider},
+    abi::{encode, Token},
+    providers::{JsonRpcClient, Middleware, Provider},
     types::{H160, H256, U256},
+    utils::keccak256,
 };
 
-#[derive(Debug)]
+//Curve pools hold at most eight coins
+const MAX_CURVE_COINS: usize = 8;
+
+//Placeholder address used by Curve pools that hold native ether
+const CURVE_ETH_ADDRESS: &str = "0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE";
+
+//Storage slot of the pools mapping in the UniswapV4 PoolManager
+const UNISWAP_V4_POOLS_SLOT: u64 = 6;
+//Offset of the liquidity slot from the start of a UniswapV4 pool's state
+const UNISWAP_V4_LIQUIDITY_OFFSET: u64 = 3;
+//Fee value that marks a UniswapV4 pool's fee as set by its hooks
+pub const UNISWAP_V4_DYNAMIC_FEE_FLAG: u32 = 0x800000;
+
+#[derive(Debug, Clone)]
 pub struct Pool {
     pub address: H160,
     pub token_a: H160,
@@ -18,15 +39,79 @@ pub struct Pool {
     pub reserve_1: u128,
     pub fee: u32,
     pub pool_variant: PoolVariant,
+    //Every token held by the pool along with its decimals and balance, in the order used by the pool contract.
+    //For two token pools these mirror token_a/token_b and reserve_0/reserve_1.
+    pub tokens: Vec<H160>,
+    pub token_decimals: Vec<u8>,
+    pub balances: Vec<u128>,
+    //Rate for each token, used by stable pools to normalize balances to 18 decimals
+    pub rates: Vec<U256>,
+    //Amplification coefficient of stable pools
+    pub amplification_coefficient: u64,
+    //Normalized weight of each token in a Balancer weighted pool, as an 18 decimal fixed point number
+    pub weights: Vec<u128>,
+    //Id of pools that are identified by the vault or singleton they are registered with (Balancer, UniswapV4)
+    pub pool_id: H256,
+    //Whether a Solidly pair uses the stable x^3 * y + y^3 * x curve instead of x * y = k
+    pub stable: bool,
+    //Hooks contract of a UniswapV4 pool
+    pub hooks: H160,
+    //Whether a UniswapV4 pool's fee is set dynamically by its hooks
+    pub dynamic_fee: bool,
+    //Tick spacing of UniswapV3 and UniswapV4 pools
+    pub tick_spacing: i32,
+    //Block and transaction the pool was created in, populated for pools discovered from creation logs
+    pub creation_block: u64,
+    pub creation_tx_hash: H256,
+    //Factory, registry or vault the pool was discovered from
+    pub factory_address: H160,
+}
+
+//Identifies a pool. UniswapV4 pools share the PoolManager address, so they are identified by pool id instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PoolKey {
+    Address(H160),
+    PoolId(H256),
+}
+
+impl From<H160> for PoolKey {
+    fn from(address: H160) -> PoolKey {
+        PoolKey::Address(address)
+    }
+}
+
+impl From<H256> for PoolKey {
+    fn from(pool_id: H256) -> PoolKey {
+        PoolKey::PoolId(pool_id)
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PoolVariant {
     UniswapV2,
     UniswapV3,
+    //Curve StableSwap pools (plain, lending and meta pools) discovered through a Curve registry or factory
+    Curve,
+    //Balancer V2 weighted and stable pools registered with the Balancer Vault
+    BalancerV2,
+    //Solidly forks (Velodrome, Aerodrome) with stable and volatile pairs
+    Solidly,
+    //UniswapV4 pools held by the singleton PoolManager. The pool address is the PoolManager
+    //and each pool is identified by its pool id.
+    UniswapV4,
+    //Algebra concentrated liquidity pools (QuickSwap V3, Camelot V3) with dynamic fees
+    Algebra,
 }
 
 impl Pool {
+    //Gets the key identifying the pool, see `PoolKey`
+    pub fn get_pool_key(&self) -> PoolKey {
+        match self.pool_variant {
+            PoolVariant::UniswapV4 => PoolKey::PoolId(self.pool_id),
+            _ => PoolKey::Address(self.address),
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         address: H160,
@@ -51,6 +136,20 @@ impl Pool {
             reserve_1,
             fee,
             pool_variant,
+            tokens: vec![],
+            token_decimals: vec![],
+            balances: vec![],
+            rates: vec![],
+            amplification_coefficient: 0,
+            weights: vec![],
+            pool_id: H256::zero(),
+            stable: false,
+            hooks: H160::zero(),
+            dynamic_fee: false,
+            tick_spacing: 0,
+            creation_block: 0,
+            creation_tx_hash: H256::zero(),
+            factory_address: H160::zero(),
         }
     }
 
@@ -66,6 +165,28 @@ impl Pool {
             reserve_1: 0,
             fee: 0,
             pool_variant,
+            tokens: vec![],
+            token_decimals: vec![],
+            balances: vec![],
+            rates: vec![],
+            amplification_coefficient: 0,
+            weights: vec![],
+            pool_id: H256::zero(),
+            stable: false,
+            hooks: H160::zero(),
+            dynamic_fee: false,
+            tick_spacing: 0,
+            creation_block: 0,
+            creation_tx_hash: H256::zero(),
+            factory_address: H160::zero(),
+        }
+    }
+
+    //Creates a Curve pool at the given address, the remaining fields are populated by `update_curve_state`
+    pub fn new_curve_pool(address: H160) -> Pool {
+        Pool {
+            address,
+            ..Pool::empty_pool(PoolVariant::Curve)
         }
     }
 
@@ -85,9 +206,27 @@ impl Pool {
             reserve_0: 0,
             reserve_1: 0,
             fee,
-            pool_variant,
+            ..Pool::empty_pool(pool_variant)
         };
 
+        //Multi token pools are populated from the pool contract itself, including the fee
+        match pool_variant {
+            PoolVariant::Curve => {
+                pool.update_curve_state(provider).await?;
+                return Ok(pool);
+            }
+            PoolVariant::BalancerV2 => {
+                pool.update_balancer_state(provider).await?;
+                return Ok(pool);
+            }
+            //UniswapV4 pools share the PoolManager address and their tokens are only known from the Initialize log
+            PoolVariant::UniswapV4 => return Err(PairSyncError::InvalidPool(pair_address)),
+            PoolVariant::UniswapV2
+            | PoolVariant::UniswapV3
+            | PoolVariant::Solidly
+            | PoolVariant::Algebra => {}
+        }
+
         pool.token_a = pool_variant
             .get_token_0(pair_address, provider.clone())
             .await?;
@@ -97,11 +236,37 @@ impl Pool {
 
         pool.update_token_decimals(provider.clone()).await?;
         pool.update_a_to_b(provider.clone()).await?;
+
+        if let PoolVariant::Solidly = pool_variant {
+            pool.stable = abi::ISolidlyPair::new(pair_address, provider.clone())
+                .stable()
+                .call()
+                .await?;
+        }
+
+        if let PoolVariant::UniswapV3 = pool_variant {
+            pool.tick_spacing = abi::IUniswapV3Pool::new(pair_address, provider.clone())
+                .tick_spacing()
+                .call()
+                .await?;
+        }
+
         pool.update_reserves(provider).await?;
 
         Ok(pool)
     }
 
+    //Creates a new pool from an address without knowing the pool variant or fee.
+    //The pool variant and fee are inferred by probing the interfaces implemented by the contract.
+    pub async fn new_pool_from_unknown_address<P: 'static + JsonRpcClient>(
+        pool_address: H160,
+        provider: Arc<Provider<P>>,
+    ) -> Result<Pool, PairSyncError<P>> {
+        let (pool_variant, fee) = detect_pool_variant(pool_address, provider.clone()).await?;
+
+        Pool::new_pool_from_address(pool_address, fee, pool_variant, provider).await
+    }
+
     pub fn is_empty(&self) -> bool {
         self.token_a == H160::zero()
     }
@@ -115,6 +280,16 @@ impl Pool {
         provider: Arc<Provider<P>>,
     ) -> Result<(u128, u128), PairSyncError<P>>
 where {
+        //UniswapV4 pools are read from the PoolManager by pool id
+        if let PoolVariant::UniswapV4 = self.pool_variant {
+            let (sqrt_price, liquidity, _) =
+                get_uniswap_v4_state(self.address, self.pool_id, provider).await?;
+
+            return Ok(concentrated_liquidity::get_virtual_reserves(
+                liquidity, sqrt_price,
+            ));
+        }
+
         self.pool_variant.get_reserves(self.address, provider).await
     }
 
@@ -122,13 +297,23 @@ where {
         &mut self,
         provider: Arc<Provider<P>>,
     ) -> Result<(), PairSyncError<P>> {
-        let (reserve0, reserve1) = self
-            .pool_variant
-            .get_reserves(self.address, provider)
-            .await?;
+        //Multi token pools need every balance to simulate swaps, not just the first two
+        match self.pool_variant {
+            PoolVariant::Curve => return self.update_curve_state(provider).await,
+            PoolVariant::BalancerV2 => return self.update_balancer_state(provider).await,
+            //Algebra fees change with volatility, so the fee is updated along with the reserves
+            PoolVariant::Algebra => return self.update_algebra_state(provider).await,
+            PoolVariant::UniswapV2
+            | PoolVariant::UniswapV3
+            | PoolVariant::Solidly
+            | PoolVariant::UniswapV4 => {}
+        }
+
+        let (reserve0, reserve1) = self.get_reserves(provider).await?;
 
         self.reserve_0 = reserve0;
         self.reserve_1 = reserve1;
+        self.update_token_vectors();
 
         Ok(())
     }
@@ -137,6 +322,11 @@ where {
         &self,
         provider: Arc<Provider<P>>,
     ) -> Result<H160, PairSyncError<P>> {
+        //UniswapV4 tokens are not stored onchain, currency0 is recorded as token_a from the Initialize log
+        if let PoolVariant::UniswapV4 = self.pool_variant {
+            return Ok(self.token_a);
+        }
+
         self.pool_variant.get_token_0(self.address, provider).await
     }
 
@@ -144,12 +334,10 @@ where {
         &mut self,
         provider: Arc<Provider<P>>,
     ) -> Result<(), PairSyncError<P>> {
-        let token0 = self
-            .pool_variant
-            .get_token_0(self.address, provider)
-            .await?;
+        let token0 = self.get_token_0(provider).await?;
 
         self.a_to_b = token0 == self.token_a;
+        self.update_token_vectors();
 
         Ok(())
     }
@@ -162,10 +350,19 @@ where {
     where
         P: JsonRpcClient,
     {
-        let (reserve_0, reserve_1) = self.get_reserves(provider.clone()).await?;
-
         match self.pool_variant {
-            PoolVariant::UniswapV2 => {
+            PoolVariant::Solidly if self.stable => {
+                let mut pool = self.clone();
+                pool.update_reserves(provider).await?;
+
+                let (token_in, token_out) = self.get_price_tokens(a_per_b);
+                Ok(pool.get_solidly_stable_price(token_in, token_out)?)
+            }
+
+            //Solidly volatile pairs are priced the same as UniswapV2 pairs
+            PoolVariant::UniswapV2 | PoolVariant::Solidly => {
+                let (reserve_0, reserve_1) = self.get_reserves(provider.clone()).await?;
+
                 if self.a_to_b {
                     let reserve_0 = reserve_0 as f64 / 10f64.powf(self.token_a_decimals.into());
                     let reserve_1 = reserve_1 as f64 / 10f64.powf(self.token_b_decimals.into());
@@ -187,7 +384,10 @@ where {
                 }
             }
 
-            PoolVariant::UniswapV3 => {
+            //UniswapV4 and Algebra pools use the same concentrated liquidity math as UniswapV3 pools
+            PoolVariant::UniswapV3 | PoolVariant::UniswapV4 | PoolVariant::Algebra => {
+                let (reserve_0, reserve_1) = self.get_reserves(provider.clone()).await?;
+
                 if self.a_to_b {
                     let reserve_0 = reserve_0 as f64 / 10f64.powf(self.token_a_decimals.into());
                     let reserve_1 = reserve_1 as f64 / 10f64.powf(self.token_b_decimals.into());
@@ -208,9 +408,566 @@ where {
                     }
                 }
             }
+
+            PoolVariant::Curve => {
+                let mut pool = self.clone();
+                pool.update_curve_state(provider).await?;
+
+                let (token_in, token_out) = self.get_price_tokens(a_per_b);
+                Ok(pool.get_stable_swap_price(token_in, token_out)?)
+            }
+
+            PoolVariant::BalancerV2 => {
+                let mut pool = self.clone();
+                pool.update_balancer_state(provider).await?;
+
+                //Stable pools do not have weights and are priced with the StableSwap invariant
+                let (token_in, token_out) = self.get_price_tokens(a_per_b);
+                if pool.weights.is_empty() {
+                    Ok(pool.get_stable_swap_price(token_in, token_out)?)
+                } else {
+                    Ok(pool.get_weighted_price(token_in, token_out)?)
+                }
+            }
+        }
+    }
+
+    //Gets the spot price of token_in in units of token_out from the pool's current state, without any calls to the node.
+    //The pool must be synced, and concentrated liquidity pools are priced from their virtual reserves.
+    pub fn get_spot_price(
+        &self,
+        token_in: H160,
+        token_out: H160,
+    ) -> Result<f64, SwapSimulationError> {
+        match self.pool_variant {
+            PoolVariant::Solidly if self.stable => {
+                self.get_solidly_stable_price(token_in, token_out)
+            }
+
+            PoolVariant::UniswapV2
+            | PoolVariant::UniswapV3
+            | PoolVariant::UniswapV4
+            | PoolVariant::Algebra
+            | PoolVariant::Solidly => {
+                let (reserve_in, reserve_out) = self.get_reserves_in_out(token_in, token_out)?;
+                let (decimals_in, decimals_out) = if token_in == self.token_a {
+                    (self.token_a_decimals, self.token_b_decimals)
+                } else {
+                    (self.token_b_decimals, self.token_a_decimals)
+                };
+
+                Ok((reserve_out as f64 / 10f64.powf(decimals_out.into()))
+                    / (reserve_in as f64 / 10f64.powf(decimals_in.into())))
+            }
+
+            PoolVariant::BalancerV2 if !self.weights.is_empty() => {
+                self.get_weighted_price(token_in, token_out)
+            }
+
+            PoolVariant::Curve | PoolVariant::BalancerV2 => {
+                self.get_stable_swap_price(token_in, token_out)
+            }
         }
     }
 
+    //Gets each token held by the pool with its decimals and balance, falling back to token_a and token_b for pools without a token list
+    pub fn get_token_balances(&self) -> Vec<(H160, u8, u128)> {
+        if !self.tokens.is_empty() {
+            return self
+                .tokens
+                .iter()
+                .zip(self.token_decimals.iter())
+                .zip(self.balances.iter())
+                .map(|((token, decimals), balance)| (*token, *decimals, *balance))
+                .collect();
+        }
+
+        let (token_a_reserves, token_b_reserves) = if self.a_to_b {
+            (self.reserve_0, self.reserve_1)
+        } else {
+            (self.reserve_1, self.reserve_0)
+        };
+
+        vec![
+            (self.token_a, self.token_a_decimals, token_a_reserves),
+            (self.token_b, self.token_b_decimals, token_b_reserves),
+        ]
+    }
+
+    //Gets the token priced and the token it is priced in, so a_per_b prices token_b in units of token_a
+    fn get_price_tokens(&self, a_per_b: bool) -> (H160, H160) {
+        if a_per_b {
+            (self.token_b, self.token_a)
+        } else {
+            (self.token_a, self.token_b)
+        }
+    }
+
+    //Simulates a swap of `amount_in` of `token_in` for `token_out` against the current pool state.
+    //UniswapV3, UniswapV4 and Algebra swaps are simulated within the current tick range only, and return
+    //`SwapSimulationError::TickRangeExceeded` for swaps that would cross a tick, see `get_amount_out_within_tick`.
+    pub fn get_amount_out(
+        &self,
+        token_in: H160,
+        token_out: H160,
+        amount_in: U256,
+    ) -> Result<U256, SwapSimulationError> {
+        match self.pool_variant {
+            PoolVariant::UniswapV2 => {
+                let (reserve_in, reserve_out) = self.get_reserves_in_out(token_in, token_out)?;
+
+                //UniswapV2 fees are expressed in thousandths of a percent
+                constant_product::get_amount_out(
+                    amount_in,
+                    U256::from(reserve_in),
+                    U256::from(reserve_out),
+                    self.fee,
+                    100_000,
+                )
+            }
+
+            PoolVariant::UniswapV3 | PoolVariant::UniswapV4 | PoolVariant::Algebra => {
+                let (reserve_in, reserve_out) = self.get_reserves_in_out(token_in, token_out)?;
+
+                //Swaps of token0 for token1 move the price down
+                let token_0 = if self.a_to_b {
+                    self.token_a
+                } else {
+                    self.token_b
+                };
+
+                concentrated_liquidity::get_amount_out_within_tick(
+                    amount_in,
+                    reserve_in,
+                    reserve_out,
+                    token_in == token_0,
+                    self.fee,
+                    self.tick_spacing,
+                )
+            }
+
+            PoolVariant::Solidly => {
+                let (reserve_in, reserve_out) = self.get_reserves_in_out(token_in, token_out)?;
+
+                //Solidly fees are expressed in basis points and charged on the amount in
+                if self.fee > 10_000 {
+                    return Err(SwapSimulationError::InvalidFee(self.fee));
+                }
+                let amount_in = amount_in - amount_in * U256::from(self.fee) / U256::from(10_000);
+
+                if self.stable {
+                    let (decimals_in, decimals_out) = if token_in == self.token_a {
+                        (self.token_a_decimals, self.token_b_decimals)
+                    } else {
+                        (self.token_b_decimals, self.token_a_decimals)
+                    };
+
+                    solidly::get_amount_out_stable(
+                        amount_in,
+                        U256::from(reserve_in),
+                        U256::from(reserve_out),
+                        decimals_in,
+                        decimals_out,
+                    )
+                } else {
+                    constant_product::get_amount_out(
+                        amount_in,
+                        U256::from(reserve_in),
+                        U256::from(reserve_out),
+                        0,
+                        10_000,
+                    )
+                }
+            }
+
+            PoolVariant::Curve => {
+                let (i, j) = self.get_token_indices(token_in, token_out)?;
+
+                stable_swap::get_dy(
+                    i,
+                    j,
+                    amount_in,
+                    &self
+                        .balances
+                        .iter()
+                        .map(|b| U256::from(*b))
+                        .collect::<Vec<U256>>(),
+                    &self.rates,
+                    U256::from(self.amplification_coefficient),
+                    U256::from(self.fee),
+                )
+            }
+
+            PoolVariant::BalancerV2 => {
+                let (i, j) = self.get_token_indices(token_in, token_out)?;
+
+                //Balancer charges the swap fee on the amount in, the fee is in hundredths of a bip
+                let fee_complement = 1_000_000u32
+                    .checked_sub(self.fee)
+                    .ok_or(SwapSimulationError::InvalidFee(self.fee))?;
+                let amount_in = amount_in * U256::from(fee_complement) / U256::from(1_000_000);
+
+                //Stable pools do not have weights and use the StableSwap invariant
+                if self.weights.is_empty() {
+                    stable_swap::get_dy(
+                        i,
+                        j,
+                        amount_in,
+                        &self
+                            .balances
+                            .iter()
+                            .map(|b| U256::from(*b))
+                            .collect::<Vec<U256>>(),
+                        &self.rates,
+                        U256::from(self.amplification_coefficient),
+                        U256::zero(),
+                    )
+                } else {
+                    weighted::get_amount_out(
+                        U256::from(self.balances[i]),
+                        U256::from(self.weights[i]),
+                        U256::from(self.balances[j]),
+                        U256::from(self.weights[j]),
+                        amount_in,
+                    )
+                }
+            }
+        }
+    }
+
+    //Gets the price of token_in in units of token_out by simulating a swap of one whole token without fees
+    fn get_stable_swap_price(
+        &self,
+        token_in: H160,
+        token_out: H160,
+    ) -> Result<f64, SwapSimulationError> {
+        let (i, j) = self.get_token_indices(token_in, token_out)?;
+
+        let amount_out = stable_swap::get_dy(
+            i,
+            j,
+            U256::exp10(self.token_decimals[i].into()),
+            &self
+                .balances
+                .iter()
+                .map(|b| U256::from(*b))
+                .collect::<Vec<U256>>(),
+            &self.rates,
+            U256::from(self.amplification_coefficient),
+            U256::zero(),
+        )?;
+
+        Ok(u256_to_f64(amount_out) / 10f64.powf(self.token_decimals[j].into()))
+    }
+
+    //Gets the reserves of token_in and token_out for pools that hold two tokens
+    fn get_reserves_in_out(
+        &self,
+        token_in: H160,
+        token_out: H160,
+    ) -> Result<(u128, u128), SwapSimulationError> {
+        let (token_0, token_1) = if self.a_to_b {
+            (self.token_a, self.token_b)
+        } else {
+            (self.token_b, self.token_a)
+        };
+
+        if token_in == token_0 && token_out == token_1 {
+            Ok((self.reserve_0, self.reserve_1))
+        } else if token_in == token_1 && token_out == token_0 {
+            Ok((self.reserve_1, self.reserve_0))
+        } else if token_in != token_0 && token_in != token_1 {
+            Err(SwapSimulationError::TokenNotInPool(token_in))
+        } else {
+            Err(SwapSimulationError::TokenNotInPool(token_out))
+        }
+    }
+
+    //Gets the price of token_in in units of token_out of a Solidly stable pair by simulating a swap of one whole token without fees
+    fn get_solidly_stable_price(
+        &self,
+        token_in: H160,
+        token_out: H160,
+    ) -> Result<f64, SwapSimulationError> {
+        let (decimals_in, decimals_out) = if token_in == self.token_a {
+            (self.token_a_decimals, self.token_b_decimals)
+        } else {
+            (self.token_b_decimals, self.token_a_decimals)
+        };
+
+        let (reserve_in, reserve_out) = self.get_reserves_in_out(token_in, token_out)?;
+
+        let amount_out = solidly::get_amount_out_stable(
+            U256::exp10(decimals_in.into()),
+            U256::from(reserve_in),
+            U256::from(reserve_out),
+            decimals_in,
+            decimals_out,
+        )?;
+
+        Ok(u256_to_f64(amount_out) / 10f64.powf(decimals_out.into()))
+    }
+
+    //Gets the spot price of token_in in units of token_out of a weighted pool, excluding fees
+    fn get_weighted_price(
+        &self,
+        token_in: H160,
+        token_out: H160,
+    ) -> Result<f64, SwapSimulationError> {
+        let (i, j) = self.get_token_indices(token_in, token_out)?;
+
+        let balance_in = self.balances[i] as f64 / 10f64.powf(self.token_decimals[i].into());
+        let balance_out = self.balances[j] as f64 / 10f64.powf(self.token_decimals[j].into());
+
+        Ok(weighted::get_spot_price(
+            balance_in,
+            self.weights[i] as f64,
+            balance_out,
+            self.weights[j] as f64,
+        ))
+    }
+
+    fn get_token_indices(
+        &self,
+        token_in: H160,
+        token_out: H160,
+    ) -> Result<(usize, usize), SwapSimulationError> {
+        let i = self
+            .tokens
+            .iter()
+            .position(|token| *token == token_in)
+            .ok_or(SwapSimulationError::TokenNotInPool(token_in))?;
+
+        let j = self
+            .tokens
+            .iter()
+            .position(|token| *token == token_out)
+            .ok_or(SwapSimulationError::TokenNotInPool(token_out))?;
+
+        Ok((i, j))
+    }
+
+    //Populates the coins, balances, rates, amplification coefficient and fee of a Curve pool
+    pub async fn update_curve_state<P: JsonRpcClient>(
+        &mut self,
+        provider: Arc<Provider<P>>,
+    ) -> Result<(), PairSyncError<P>> {
+        let curve_pool = abi::ICurvePool::new(self.address, provider.clone());
+
+        //Get every coin in the pool, the coin list ends when the call reverts
+        let mut tokens = vec![];
+        for i in 0..MAX_CURVE_COINS {
+            match get_curve_coin(self.address, i, provider.clone()).await {
+                Ok(coin) => tokens.push(coin),
+                Err(pair_sync_error) if pair_sync_error.is_revert() => break,
+                Err(pair_sync_error) => return Err(pair_sync_error),
+            }
+        }
+
+        if tokens.len() < 2 {
+            return Err(PairSyncError::InvalidPool(self.address));
+        }
+
+        let mut token_decimals = vec![];
+        let mut balances = vec![];
+        for (i, token) in tokens.iter().enumerate() {
+            token_decimals.push(get_token_decimals(*token, provider.clone()).await?);
+            let balance = get_curve_balance(self.address, i, provider.clone()).await?;
+            balances.push(
+                u128::try_from(balance).map_err(|_| PairSyncError::InvalidPool(self.address))?,
+            );
+        }
+
+        //Rates normalize each balance to 18 decimals. Plain pools only need to account for decimals.
+        let mut rates = vec![];
+        for decimals in token_decimals.iter() {
+            let rate_decimals = 36_usize
+                .checked_sub(*decimals as usize)
+                .ok_or(PairSyncError::InvalidPool(self.address))?;
+            rates.push(U256::exp10(rate_decimals));
+        }
+
+        if let Ok(base_pool) = curve_pool.base_pool().call().await {
+            //Meta pools price the base pool LP token (always the last coin) at the base pool virtual price
+            let virtual_price = abi::ICurvePool::new(base_pool, provider.clone())
+                .get_virtual_price()
+                .call()
+                .await?;
+
+            if let Some(rate) = rates.last_mut() {
+                *rate = virtual_price;
+            }
+        } else if get_curve_underlying_coin(self.address, 0, provider.clone())
+            .await
+            .is_ok()
+        {
+            //Lending pools hold wrapped tokens, so the rate is the wrapped token exchange rate scaled by the underlying decimals
+            for (i, token) in tokens.iter().enumerate() {
+                let underlying_coin =
+                    get_curve_underlying_coin(self.address, i, provider.clone()).await?;
+                let underlying_decimals =
+                    get_token_decimals(underlying_coin, provider.clone()).await?;
+
+                let lending_token = abi::ICurveLendingToken::new(*token, provider.clone());
+                let exchange_rate =
+                    if let Ok(rate) = lending_token.exchange_rate_stored().call().await {
+                        rate
+                    } else if let Ok(rate) = lending_token.get_price_per_full_share().call().await {
+                        rate
+                    } else {
+                        U256::exp10(18)
+                    };
+
+                let underlying_scale = 18_usize
+                    .checked_sub(underlying_decimals as usize)
+                    .ok_or(PairSyncError::InvalidPool(self.address))?;
+                rates[i] = exchange_rate
+                    .checked_mul(U256::exp10(underlying_scale))
+                    .ok_or(PairSyncError::InvalidPool(self.address))?;
+            }
+        }
+
+        //Curve fees have 10 decimals and are capped at 5e9, so fees above u32::MAX are rejected rather than truncated
+        self.amplification_coefficient = u64::try_from(curve_pool.a().call().await?)
+            .map_err(|_| PairSyncError::InvalidPool(self.address))?;
+        self.fee = u32::try_from(curve_pool.fee().call().await?)
+            .map_err(|_| PairSyncError::InvalidPool(self.address))?;
+
+        self.token_a = tokens[0];
+        self.token_a_decimals = token_decimals[0];
+        self.token_b = tokens[1];
+        self.token_b_decimals = token_decimals[1];
+        self.a_to_b = true;
+        self.reserve_0 = balances[0];
+        self.reserve_1 = balances[1];
+
+        self.tokens = tokens;
+        self.token_decimals = token_decimals;
+        self.balances = balances;
+        self.rates = rates;
+
+        Ok(())
+    }
+
+    //Populates the tokens, balances, weights or amplification coefficient, and swap fee of a Balancer pool
+    pub async fn update_balancer_state<P: JsonRpcClient>(
+        &mut self,
+        provider: Arc<Provider<P>>,
+    ) -> Result<(), PairSyncError<P>> {
+        let balancer_pool = abi::IBalancerPool::new(self.address, provider.clone());
+
+        if self.pool_id.is_zero() {
+            self.pool_id = H256::from(balancer_pool.get_pool_id().call().await?);
+        }
+
+        let (tokens, balances) =
+            get_balancer_pool_tokens(self.address, self.pool_id, provider.clone()).await?;
+
+        let mut token_decimals = vec![];
+        for token in tokens.iter() {
+            token_decimals.push(get_token_decimals(*token, provider.clone()).await?);
+        }
+
+        let invalid_pool = || PairSyncError::InvalidPool(self.address);
+
+        //Balances that do not fit in a u128 can not be stored on the pool
+        let balances = balances
+            .iter()
+            .map(|balance| u128::try_from(*balance).map_err(|_| invalid_pool()))
+            .collect::<Result<Vec<u128>, _>>()?;
+
+        //Weighted pools expose normalized weights, stable pools expose an amplification parameter instead
+        let weights = match balancer_pool.get_normalized_weights().call().await {
+            Ok(weights) => weights
+                .iter()
+                .map(|weight| u128::try_from(*weight).map_err(|_| invalid_pool()))
+                .collect::<Result<Vec<u128>, _>>()?,
+            Err(_) => {
+                let (value, _, precision) =
+                    balancer_pool.get_amplification_parameter().call().await?;
+                self.amplification_coefficient =
+                    u64::try_from(value.checked_div(precision).ok_or_else(invalid_pool)?)
+                        .map_err(|_| invalid_pool())?;
+
+                vec![]
+            }
+        };
+
+        //The swap fee percentage is an 18 decimal fixed point number, converted to hundredths of a bip
+        let fee =
+            u32::try_from(balancer_pool.get_swap_fee_percentage().call().await? / U256::exp10(12))
+                .map_err(|_| invalid_pool())?;
+
+        let mut rates = vec![];
+        for decimals in token_decimals.iter() {
+            let rate_decimals = 36_usize
+                .checked_sub(*decimals as usize)
+                .ok_or_else(invalid_pool)?;
+            rates.push(U256::exp10(rate_decimals));
+        }
+
+        self.weights = weights;
+        self.fee = fee;
+
+        self.token_a = tokens[0];
+        self.token_a_decimals = token_decimals[0];
+        self.token_b = tokens[1];
+        self.token_b_decimals = token_decimals[1];
+        self.a_to_b = true;
+        self.reserve_0 = balances[0];
+        self.reserve_1 = balances[1];
+
+        self.rates = rates;
+        self.tokens = tokens;
+        self.token_decimals = token_decimals;
+        self.balances = balances;
+
+        Ok(())
+    }
+
+    //Populates the reserves, current fee and token decimals of a UniswapV4 pool from the PoolManager
+    pub async fn update_uniswap_v4_state<P: JsonRpcClient>(
+        &mut self,
+        provider: Arc<Provider<P>>,
+    ) -> Result<(), PairSyncError<P>> {
+        let (sqrt_price, liquidity, lp_fee) =
+            get_uniswap_v4_state(self.address, self.pool_id, provider.clone()).await?;
+
+        (self.reserve_0, self.reserve_1) =
+            concentrated_liquidity::get_virtual_reserves(liquidity, sqrt_price);
+
+        //The lp fee in slot0 is the fee currently charged, which is also set for dynamic fee pools
+        self.fee = lp_fee;
+
+        self.token_a_decimals = get_token_decimals(self.token_a, provider.clone()).await?;
+        self.token_b_decimals = get_token_decimals(self.token_b, provider).await?;
+        self.update_token_vectors();
+
+        Ok(())
+    }
+
+    //Populates the reserves and current dynamic fee of an Algebra pool, and the tick spacing if it is not set
+    pub async fn update_algebra_state<P: JsonRpcClient>(
+        &mut self,
+        provider: Arc<Provider<P>>,
+    ) -> Result<(), PairSyncError<P>> {
+        let algebra_pool = abi::IAlgebraPool::new(self.address, provider);
+
+        if self.tick_spacing == 0 {
+            self.tick_spacing = algebra_pool.tick_spacing().call().await?;
+        }
+
+        let liquidity = algebra_pool.liquidity().call().await?;
+        let (sqrt_price, _, fee) = algebra_pool.global_state().call().await?;
+
+        (self.reserve_0, self.reserve_1) =
+            concentrated_liquidity::get_virtual_reserves(liquidity, sqrt_price);
+        self.fee = fee.into();
+        self.update_token_vectors();
+
+        Ok(())
+    }
+
     pub async fn update_token_decimals<P: 'static + JsonRpcClient>(
         &mut self,
         provider: Arc<Provider<P>>,
@@ -225,8 +982,34 @@ where {
             .call()
             .await?;
 
+        self.update_token_vectors();
+
         Ok(())
     }
+
+    //Mirrors token_a/token_b and their reserves into the token vectors, ordered as token0/token1
+    pub(crate) fn update_token_vectors(&mut self) {
+        match self.pool_variant {
+            PoolVariant::UniswapV2
+            | PoolVariant::UniswapV3
+            | PoolVariant::Solidly
+            | PoolVariant::UniswapV4
+            | PoolVariant::Algebra => {
+                if self.a_to_b {
+                    self.tokens = vec![self.token_a, self.token_b];
+                    self.token_decimals = vec![self.token_a_decimals, self.token_b_decimals];
+                } else {
+                    self.tokens = vec![self.token_b, self.token_a];
+                    self.token_decimals = vec![self.token_b_decimals, self.token_a_decimals];
+                }
+
+                self.balances = vec![self.reserve_0, self.reserve_1];
+            }
+
+            //Multi token pools populate the token vectors directly from the pool state
+            PoolVariant::Curve | PoolVariant::BalancerV2 => {}
+        }
+    }
 }
 
 impl PoolVariant {
@@ -240,6 +1023,43 @@ impl PoolVariant {
                 H256::from_str("0x783cca1c0412dd0d695e784568c96da2e9c22ff989357a2e8b1d9b2b4e6b7118")
                     .unwrap()
             }
+            //Curve registries emit PoolAdded, factories are enumerated through pool_list
+            PoolVariant::Curve => {
+                H256::from_str("0xe485c16479ab7092c0b3fc4649843c06be7f072194675261590c84473ab0aea9")
+                    .unwrap()
+            }
+            //Balancer pools are registered with the Vault rather than created by a factory
+            PoolVariant::BalancerV2 => {
+                H256::from_str("0x3c13bc30b8e878c53fd2a36b679409c073afd75950be43d8858768e956fbc20e")
+                    .unwrap()
+            }
+            PoolVariant::Solidly => {
+                H256::from_str("0xc4805696c66d7cf352fc1d6bb633ad5ee82f6cb577c453024b6e0eb8306c6fc9")
+                    .unwrap()
+            }
+            PoolVariant::UniswapV4 => {
+                H256::from_str("0xdd466e674ea557f56295e2d0218a125ea4b4f0f6f3307b95f85e6110838d6438")
+                    .unwrap()
+            }
+            PoolVariant::Algebra => {
+                H256::from_str("0x91ccaa7a278130b65168c3a0c8d3bcae84cf5e43704342bd3ec0b59e59c036db")
+                    .unwrap()
+            }
+        }
+    }
+
+    //Gets every pool created event signature of the variant. Aerodrome and Velodrome V2 factories emit
+    //`PoolCreated(address,address,bool,address,uint256)` instead of the Solidly `PairCreated` event.
+    pub fn pool_created_event_signatures(&self) -> Vec<H256> {
+        match self {
+            PoolVariant::Solidly => vec![
+                self.pool_created_event_signature(),
+                H256::from_str(
+                    "0x2128d88d14c80cb081c1252a5acff7a264671bf199ce226b53788fb26065005e",
+                )
+                .unwrap(),
+            ],
+            _ => vec![self.pool_created_event_signature()],
         }
     }
 
@@ -249,7 +1069,8 @@ impl PoolVariant {
         provider: Arc<Provider<P>>,
     ) -> Result<(u128, u128), PairSyncError<P>> {
         match self {
-            PoolVariant::UniswapV2 => {
+            //Solidly pairs share the UniswapV2 reserves interface
+            PoolVariant::UniswapV2 | PoolVariant::Solidly => {
                 //Initialize a new instance of the Pool
                 let v2_pair = abi::IUniswapV2Pair::new(pair_address, provider);
 
@@ -267,24 +1088,48 @@ impl PoolVariant {
             PoolVariant::UniswapV3 => {
                 let v3_pool = abi::IUniswapV3Pool::new(pair_address, provider.clone());
 
-                let liquidity =
-                    U256::from_big_endian(&v3_pool.liquidity().call().await?.to_be_bytes());
+                let liquidity = v3_pool.liquidity().call().await?;
                 let slot_0 = v3_pool.slot_0().call().await?;
 
-                //Sqrt price is stored as a Q64.96 so we need to shift the sqrt_price by 96 to be represented as just the integer numbers
-                let sqrt_price = slot_0.0.shr(96);
+                Ok(concentrated_liquidity::get_virtual_reserves(
+                    liquidity, slot_0.0,
+                ))
+            }
+            //Algebra pools store the price in globalState rather than slot0
+            PoolVariant::Algebra => {
+                let algebra_pool = abi::IAlgebraPool::new(pair_address, provider);
 
-                let (reserve_0, reserve_1) = if !sqrt_price.is_zero() {
-                    let reserve_x = (liquidity / sqrt_price).as_u128();
-                    let reserve_y = (liquidity * sqrt_price).as_u128();
+                let liquidity = algebra_pool.liquidity().call().await?;
+                let (sqrt_price, _, _) = algebra_pool.global_state().call().await?;
 
-                    (reserve_x, reserve_y)
-                } else {
-                    (0_u128, 0_u128)
-                };
+                Ok(concentrated_liquidity::get_virtual_reserves(
+                    liquidity, sqrt_price,
+                ))
+            }
+            PoolVariant::Curve => {
+                let balance_0 = get_curve_balance(pair_address, 0, provider.clone()).await?;
+                let balance_1 = get_curve_balance(pair_address, 1, provider).await?;
 
-                Ok((reserve_0, reserve_1))
+                Ok((
+                    u128::try_from(balance_0)
+                        .map_err(|_| PairSyncError::InvalidPool(pair_address))?,
+                    u128::try_from(balance_1)
+                        .map_err(|_| PairSyncError::InvalidPool(pair_address))?,
+                ))
+            }
+            PoolVariant::BalancerV2 => {
+                let (_, balances) =
+                    get_balancer_pool_tokens(pair_address, H256::zero(), provider).await?;
+
+                Ok((
+                    u128::try_from(balances[0])
+                        .map_err(|_| PairSyncError::InvalidPool(pair_address))?,
+                    u128::try_from(balances[1])
+                        .map_err(|_| PairSyncError::InvalidPool(pair_address))?,
+                ))
             }
+            //UniswapV4 pools can only be read by pool id, see `Pool::get_reserves`
+            PoolVariant::UniswapV4 => Err(PairSyncError::InvalidPool(pair_address)),
         }
     }
 
@@ -294,8 +1139,11 @@ impl PoolVariant {
         provider: Arc<Provider<P>>,
     ) -> Result<H160, PairSyncError<P>> {
         match self {
-            //Can match on v2 or v3 because they both have the same interface for token0, token1
-            PoolVariant::UniswapV2 | PoolVariant::UniswapV3 => {
+            //Can match on v2, v3, solidly or algebra because they all have the same interface for token0, token1
+            PoolVariant::UniswapV2
+            | PoolVariant::UniswapV3
+            | PoolVariant::Solidly
+            | PoolVariant::Algebra => {
                 //Initialize a new instance of the Pool
                 let v2_pair = abi::IUniswapV2Pair::new(pair_address, provider);
 
@@ -308,6 +1156,15 @@ impl PoolVariant {
                 };
                 Ok(token0)
             }
+            PoolVariant::Curve => get_curve_coin(pair_address, 0, provider).await,
+            PoolVariant::BalancerV2 => {
+                let (tokens, _) =
+                    get_balancer_pool_tokens(pair_address, H256::zero(), provider).await?;
+
+                Ok(tokens[0])
+            }
+            //UniswapV4 tokens are only known from the Initialize log, see `Pool::get_token_0`
+            PoolVariant::UniswapV4 => Err(PairSyncError::InvalidPool(pair_address)),
         }
     }
 
@@ -317,20 +1174,238 @@ impl PoolVariant {
         provider: Arc<Provider<P>>,
     ) -> Result<H160, PairSyncError<P>> {
         match self {
-            //Can match on v2 or v3 because they both have the same interface for token0, token1
-            PoolVariant::UniswapV2 | PoolVariant::UniswapV3 => {
+            //Can match on v2, v3, solidly or algebra because they all have the same interface for token0, token1
+            PoolVariant::UniswapV2
+            | PoolVariant::UniswapV3
+            | PoolVariant::Solidly
+            | PoolVariant::Algebra => {
                 //Initialize a new instance of the Pool
                 let v2_pair = abi::IUniswapV2Pair::new(pair_address, provider);
 
-                // Make a call to get token0 to initialize a_to_b
-                let token0 = match v2_pair.token_0().call().await {
+                // Make a call to get token1
+                let token1 = match v2_pair.token_1().call().await {
                     Ok(result) => result,
                     Err(contract_error) => {
                         return Err(PairSyncError::ContractError(contract_error))
                     }
                 };
-                Ok(token0)
+                Ok(token1)
+            }
+            PoolVariant::Curve => get_curve_coin(pair_address, 1, provider).await,
+            PoolVariant::BalancerV2 => {
+                let (tokens, _) =
+                    get_balancer_pool_tokens(pair_address, H256::zero(), provider).await?;
+
+                Ok(tokens[1])
             }
+            //UniswapV4 tokens are only known from the Initialize log
+            PoolVariant::UniswapV4 => Err(PairSyncError::InvalidPool(pair_address)),
         }
     }
 }
+
+//Curve pools deployed before Vyper 0.2 index coins and balances with int128 rather than uint256,
+//so each getter falls back to the legacy interface when the uint256 call fails
+async fn get_curve_coin<P: JsonRpcClient>(
+    pool_address: H160,
+    index: usize,
+    provider: Arc<Provider<P>>,
+) -> Result<H160, PairSyncError<P>> {
+    let curve_pool = abi::ICurvePool::new(pool_address, provider.clone());
+
+    match curve_pool.coins(U256::from(index)).call().await {
+        Ok(coin) => Ok(coin),
+        Err(_) => Ok(abi::ICurvePoolLegacy::new(pool_address, provider)
+            .coins(index as i128)
+            .call()
+            .await?),
+    }
+}
+
+async fn get_curve_balance<P: JsonRpcClient>(
+    pool_address: H160,
+    index: usize,
+    provider: Arc<Provider<P>>,
+) -> Result<U256, PairSyncError<P>> {
+    let curve_pool = abi::ICurvePool::new(pool_address, provider.clone());
+
+    match curve_pool.balances(U256::from(index)).call().await {
+        Ok(balance) => Ok(balance),
+        Err(_) => Ok(abi::ICurvePoolLegacy::new(pool_address, provider)
+            .balances(index as i128)
+            .call()
+            .await?),
+    }
+}
+
+async fn get_curve_underlying_coin<P: JsonRpcClient>(
+    pool_address: H160,
+    index: usize,
+    provider: Arc<Provider<P>>,
+) -> Result<H160, PairSyncError<P>> {
+    let curve_pool = abi::ICurvePool::new(pool_address, provider.clone());
+
+    match curve_pool.underlying_coins(U256::from(index)).call().await {
+        Ok(coin) => Ok(coin),
+        Err(_) => Ok(abi::ICurvePoolLegacy::new(pool_address, provider)
+            .underlying_coins(index as i128)
+            .call()
+            .await?),
+    }
+}
+
+//Gets the tokens and balances of a Balancer pool from the Vault, looking up the pool id if it is not known.
+//Composable pools register their own pool token, which is excluded since it can not be swapped like the other tokens.
+async fn get_balancer_pool_tokens<P: JsonRpcClient>(
+    pool_address: H160,
+    pool_id: H256,
+    provider: Arc<Provider<P>>,
+) -> Result<(Vec<H160>, Vec<U256>), PairSyncError<P>> {
+    let balancer_pool = abi::IBalancerPool::new(pool_address, provider.clone());
+
+    let pool_id = if pool_id.is_zero() {
+        balancer_pool.get_pool_id().call().await?
+    } else {
+        pool_id.to_fixed_bytes()
+    };
+
+    let vault_address = balancer_pool.get_vault().call().await?;
+    let (tokens, balances, _) = abi::IBalancerVault::new(vault_address, provider)
+        .get_pool_tokens(pool_id)
+        .call()
+        .await?;
+
+    let (tokens, balances): (Vec<H160>, Vec<U256>) = tokens
+        .into_iter()
+        .zip(balances)
+        .filter(|(token, _)| *token != pool_address)
+        .unzip();
+
+    if tokens.len() < 2 {
+        return Err(PairSyncError::InvalidPool(pool_address));
+    }
+
+    Ok((tokens, balances))
+}
+
+//Reads the sqrt price, liquidity and lp fee of a UniswapV4 pool from the PoolManager's storage, following the StateLibrary layout
+pub(crate) async fn get_uniswap_v4_state<P: JsonRpcClient>(
+    pool_manager: H160,
+    pool_id: H256,
+    provider: Arc<Provider<P>>,
+) -> Result<(U256, u128, u32), PairSyncError<P>> {
+    let pool_manager = abi::IUniswapV4PoolManager::new(pool_manager, provider);
+
+    let state_slot = U256::from(keccak256(encode(&[
+        Token::FixedBytes(pool_id.as_bytes().to_vec()),
+        Token::Uint(U256::from(UNISWAP_V4_POOLS_SLOT)),
+    ])));
+
+    let mut slot_0 = [0u8; 32];
+    state_slot.to_big_endian(&mut slot_0);
+    let slot_0 = U256::from(pool_manager.extsload(slot_0).call().await?);
+
+    let mut liquidity_slot = [0u8; 32];
+    (state_slot + UNISWAP_V4_LIQUIDITY_OFFSET).to_big_endian(&mut liquidity_slot);
+    let liquidity = U256::from(pool_manager.extsload(liquidity_slot).call().await?);
+
+    //Slot0 packs the sqrt price (160 bits), tick (24 bits), protocol fee (24 bits) and lp fee (24 bits)
+    let sqrt_price = slot_0 & ((U256::one() << 160) - 1);
+    let lp_fee = ((slot_0 >> 208) & U256::from(0xFFFFFF)).as_u32();
+
+    Ok((sqrt_price, liquidity.low_u128(), lp_fee))
+}
+
+//Returns true if the token is a placeholder for native ether rather than a token contract.
+//Curve uses a placeholder address and UniswapV4 uses the zero address.
+pub(crate) fn is_native_token(token: H160) -> bool {
+    token == H160::from_str(CURVE_ETH_ADDRESS).unwrap() || token.is_zero()
+}
+
+async fn get_token_decimals<P: JsonRpcClient>(
+    token: H160,
+    provider: Arc<Provider<P>>,
+) -> Result<u8, PairSyncError<P>> {
+    //Native ether does not have a contract to query
+    if is_native_token(token) {
+        return Ok(18);
+    }
+
+    Ok(abi::IErc20::new(token, provider).decimals().call().await?)
+}
+
+//Infers the pool variant and fee of a pool by probing the interfaces implemented at the address.
+//UniswapV2 pairs can not report their fee, so the fee model of their factory in the dex registry is used,
+//ie. 25 basis points for PancakeSwap, and pairs from factories outside the registry are assumed to charge 0.3%.
+pub async fn detect_pool_variant<P: JsonRpcClient>(
+    pool_address: H160,
+    provider: Arc<Provider<P>>,
+) -> Result<(PoolVariant, u32), PairSyncError<P>> {
+    if provider.get_code(pool_address, None).await?.0.is_empty() {
+        return Err(PairSyncError::ContractNotDeployed(pool_address));
+    }
+
+    //Balancer pools identify themselves with a pool id in the vault, the fee is read from the pool when it is populated
+    if abi::IBalancerPool::new(pool_address, provider.clone())
+        .get_pool_id()
+        .call()
+        .await
+        .is_ok()
+    {
+        return Ok((PoolVariant::BalancerV2, 0));
+    }
+
+    //Algebra pools keep the price and dynamic fee in globalState instead of slot0
+    if let Ok((_, _, fee)) = abi::IAlgebraPool::new(pool_address, provider.clone())
+        .global_state()
+        .call()
+        .await
+    {
+        return Ok((PoolVariant::Algebra, fee as u32));
+    }
+
+    let uniswap_v3_pool = abi::IUniswapV3Pool::new(pool_address, provider.clone());
+    if uniswap_v3_pool.slot_0().call().await.is_ok() {
+        if let Ok(fee) = uniswap_v3_pool.fee().call().await {
+            return Ok((PoolVariant::UniswapV3, fee));
+        }
+    }
+
+    let uniswap_v2_pair = abi::IUniswapV2Pair::new(pool_address, provider.clone());
+    if uniswap_v2_pair.get_reserves().call().await.is_ok() {
+        //Solidly pairs share the UniswapV2 interface but are either stable or volatile, with the fee set on the factory
+        if let Ok(stable) = abi::ISolidlyPair::new(pool_address, provider.clone())
+            .stable()
+            .call()
+            .await
+        {
+            let factory_address = uniswap_v2_pair.factory().call().await?;
+            let fee = Dex::new(factory_address, PoolVariant::Solidly, 0)
+                .get_solidly_fee(pool_address, stable, provider)
+                .await?;
+
+            return Ok((PoolVariant::Solidly, fee));
+        }
+
+        //UniswapV2 pairs can not report their fee, so the fee model of the factory's dex in the registry is used
+        let factory_address = uniswap_v2_pair.factory().call().await.ok();
+        let dex = match factory_address.and_then(registry::get_dex_by_factory) {
+            Some(dex) if dex.pool_variant == PoolVariant::UniswapV2 => dex,
+            _ => Dex::new(
+                factory_address.unwrap_or_default(),
+                PoolVariant::UniswapV2,
+                0,
+            ),
+        };
+        let fee = dex.get_uniswap_v2_fee(pool_address, provider).await?;
+
+        return Ok((PoolVariant::UniswapV2, fee));
+    }
+
+    //Curve pools expose their coins by index, the fee is read from the pool when it is populated
+    if get_curve_coin(pool_address, 0, provider).await.is_ok() {
+        return Ok((PoolVariant::Curve, 0));
+    }
+
+    Err(PairSyncError::UnknownPoolVariant(pool_address))
+}
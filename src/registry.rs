@@ -0,0 +1,468 @@
+use std::{str::FromStr, sync::Arc};
+
+use ethers::{
+    providers::{JsonRpcClient, Middleware, Provider},
+    types::{H160, H256},
+};
+
+use crate::{
+    dex::{Dex, FeeModel},
+    error::PairSyncError,
+    pool::PoolVariant,
+};
+
+//Chains with dex presets in the registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chain {
+    Ethereum,
+    Arbitrum,
+    Optimism,
+    Base,
+    Polygon,
+    Bsc,
+}
+
+impl Chain {
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            Chain::Ethereum => 1,
+            Chain::Arbitrum => 42161,
+            Chain::Optimism => 10,
+            Chain::Base => 8453,
+            Chain::Polygon => 137,
+            Chain::Bsc => 56,
+        }
+    }
+
+    pub fn from_chain_id(chain_id: u64) -> Option<Chain> {
+        match chain_id {
+            1 => Some(Chain::Ethereum),
+            42161 => Some(Chain::Arbitrum),
+            10 => Some(Chain::Optimism),
+            8453 => Some(Chain::Base),
+            137 => Some(Chain::Polygon),
+            56 => Some(Chain::Bsc),
+            _ => None,
+        }
+    }
+
+    //Gets the wrapped native token of the chain (WETH, WMATIC or WBNB), used as the base token to price tokens against
+    pub fn wrapped_native_token(&self) -> H160 {
+        let address = match self {
+            Chain::Ethereum => "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+            Chain::Arbitrum => "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1",
+            Chain::Optimism | Chain::Base => "0x4200000000000000000000000000000000000006",
+            Chain::Polygon => "0x0d500B1d8E8eF31E21C99d1Db9A6444d3ADf1270",
+            Chain::Bsc => "0xbb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c",
+        };
+
+        H160::from_str(address).unwrap()
+    }
+
+    //Gets the chain from its name, ie. "ethereum" or "bsc"
+    pub fn from_name(name: &str) -> Option<Chain> {
+        match name.to_lowercase().as_str() {
+            "ethereum" | "mainnet" => Some(Chain::Ethereum),
+            "arbitrum" => Some(Chain::Arbitrum),
+            "optimism" => Some(Chain::Optimism),
+            "base" => Some(Chain::Base),
+            "polygon" => Some(Chain::Polygon),
+            "bsc" => Some(Chain::Bsc),
+            _ => None,
+        }
+    }
+}
+
+//A known dex deployment
+struct DexPreset {
+    chain: Chain,
+    name: &'static str,
+    factory_address: &'static str,
+    pool_variant: PoolVariant,
+    creation_block: u64,
+    //Only set for UniswapV2 dexes, other pool variants read their fees from the pool or factory
+    fee_model: Option<FeeModel>,
+    init_code_hash: Option<&'static str>,
+}
+
+impl DexPreset {
+    fn to_dex(&self) -> Dex {
+        let mut dex = Dex::new(
+            H160::from_str(self.factory_address).unwrap(),
+            self.pool_variant,
+            self.creation_block,
+        );
+
+        if let Some(fee_model) = self.fee_model {
+            //Preset fee models are within bounds
+            dex = dex.with_fee_model(fee_model).unwrap();
+        }
+
+        match self.init_code_hash {
+            Some(init_code_hash) => {
+                dex.with_init_code_hash(H256::from_str(init_code_hash).unwrap())
+            }
+            None => dex,
+        }
+    }
+}
+
+const UNISWAP_V2_INIT_CODE_HASH: &str =
+    "0x96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f";
+const UNISWAP_V3_INIT_CODE_HASH: &str =
+    "0xe34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54";
+const PANCAKESWAP_V2_INIT_CODE_HASH: &str =
+    "0x00fb7f630766e6a796048ea87d01acd3068e8ff67d078148a3fa3f4a84f69bd5";
+
+//Presets cover UniswapV2, UniswapV3, UniswapV4, Curve and Balancer deployments. Solidly (Velodrome, Aerodrome) and
+//Algebra (Camelot, QuickSwap V3) deployments are not included, create them with `Dex::new_from_factory` instead.
+const DEX_PRESETS: &[DexPreset] = &[
+    //Ethereum
+    DexPreset {
+        chain: Chain::Ethereum,
+        name: "uniswap_v2",
+        factory_address: "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f",
+        pool_variant: PoolVariant::UniswapV2,
+        creation_block: 10000835,
+        fee_model: Some(FeeModel::Fixed(30)),
+        init_code_hash: Some(UNISWAP_V2_INIT_CODE_HASH),
+    },
+    DexPreset {
+        chain: Chain::Ethereum,
+        name: "sushiswap",
+        factory_address: "0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac",
+        pool_variant: PoolVariant::UniswapV2,
+        creation_block: 10794229,
+        fee_model: Some(FeeModel::Fixed(30)),
+        init_code_hash: None,
+    },
+    DexPreset {
+        chain: Chain::Ethereum,
+        name: "uniswap_v3",
+        factory_address: "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+        pool_variant: PoolVariant::UniswapV3,
+        creation_block: 12369621,
+        fee_model: None,
+        init_code_hash: Some(UNISWAP_V3_INIT_CODE_HASH),
+    },
+    DexPreset {
+        chain: Chain::Ethereum,
+        name: "uniswap_v4",
+        factory_address: "0x000000000004444c5dc75cB358380D2e3dE08A90",
+        pool_variant: PoolVariant::UniswapV4,
+        creation_block: 21688329,
+        fee_model: None,
+        init_code_hash: None,
+    },
+    DexPreset {
+        chain: Chain::Ethereum,
+        name: "curve",
+        factory_address: "0x90E00ACe148ca3b23Ac1bC8C240C2a7Dd9c2d7f5",
+        pool_variant: PoolVariant::Curve,
+        creation_block: 12195750,
+        fee_model: None,
+        init_code_hash: None,
+    },
+    DexPreset {
+        chain: Chain::Ethereum,
+        name: "balancer_v2",
+        factory_address: "0xBA12222222228d8Ba445958a75a0704d566BF2C8",
+        pool_variant: PoolVariant::BalancerV2,
+        creation_block: 12272146,
+        fee_model: None,
+        init_code_hash: None,
+    },
+    //Arbitrum
+    DexPreset {
+        chain: Chain::Arbitrum,
+        name: "uniswap_v3",
+        factory_address: "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+        pool_variant: PoolVariant::UniswapV3,
+        creation_block: 165,
+        fee_model: None,
+        init_code_hash: Some(UNISWAP_V3_INIT_CODE_HASH),
+    },
+    DexPreset {
+        chain: Chain::Arbitrum,
+        name: "sushiswap",
+        factory_address: "0xc35DADB65012eC5796536bD9864eD8773aBc74C4",
+        pool_variant: PoolVariant::UniswapV2,
+        creation_block: 70,
+        fee_model: Some(FeeModel::Fixed(30)),
+        init_code_hash: None,
+    },
+    DexPreset {
+        chain: Chain::Arbitrum,
+        name: "balancer_v2",
+        factory_address: "0xBA12222222228d8Ba445958a75a0704d566BF2C8",
+        pool_variant: PoolVariant::BalancerV2,
+        creation_block: 222832,
+        fee_model: None,
+        init_code_hash: None,
+    },
+    //Optimism
+    DexPreset {
+        chain: Chain::Optimism,
+        name: "uniswap_v3",
+        factory_address: "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+        pool_variant: PoolVariant::UniswapV3,
+        creation_block: 0,
+        fee_model: None,
+        init_code_hash: Some(UNISWAP_V3_INIT_CODE_HASH),
+    },
+    DexPreset {
+        chain: Chain::Optimism,
+        name: "balancer_v2",
+        factory_address: "0xBA12222222228d8Ba445958a75a0704d566BF2C8",
+        pool_variant: PoolVariant::BalancerV2,
+        creation_block: 7003431,
+        fee_model: None,
+        init_code_hash: None,
+    },
+    //Base
+    DexPreset {
+        chain: Chain::Base,
+        name: "uniswap_v2",
+        factory_address: "0x8909Dc15e40173Ff4699343b6eB8132c65e18eC6",
+        pool_variant: PoolVariant::UniswapV2,
+        creation_block: 6601915,
+        fee_model: Some(FeeModel::Fixed(30)),
+        init_code_hash: Some(UNISWAP_V2_INIT_CODE_HASH),
+    },
+    DexPreset {
+        chain: Chain::Base,
+        name: "uniswap_v3",
+        factory_address: "0x33128a8fC17869897dcE68Ed026d694621f6FDfD",
+        pool_variant: PoolVariant::UniswapV3,
+        creation_block: 1371680,
+        fee_model: None,
+        init_code_hash: Some(UNISWAP_V3_INIT_CODE_HASH),
+    },
+    //Polygon
+    DexPreset {
+        chain: Chain::Polygon,
+        name: "quickswap",
+        factory_address: "0x5757371414417b8C6CAad45bAeF941aBc7d3Ab32",
+        pool_variant: PoolVariant::UniswapV2,
+        creation_block: 4931780,
+        fee_model: Some(FeeModel::Fixed(30)),
+        init_code_hash: None,
+    },
+    DexPreset {
+        chain: Chain::Polygon,
+        name: "sushiswap",
+        factory_address: "0xc35DADB65012eC5796536bD9864eD8773aBc74C4",
+        pool_variant: PoolVariant::UniswapV2,
+        creation_block: 11333218,
+        fee_model: Some(FeeModel::Fixed(30)),
+        init_code_hash: None,
+    },
+    DexPreset {
+        chain: Chain::Polygon,
+        name: "uniswap_v3",
+        factory_address: "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+        pool_variant: PoolVariant::UniswapV3,
+        creation_block: 22757547,
+        fee_model: None,
+        init_code_hash: Some(UNISWAP_V3_INIT_CODE_HASH),
+    },
+    DexPreset {
+        chain: Chain::Polygon,
+        name: "balancer_v2",
+        factory_address: "0xBA12222222228d8Ba445958a75a0704d566BF2C8",
+        pool_variant: PoolVariant::BalancerV2,
+        creation_block: 15832990,
+        fee_model: None,
+        init_code_hash: None,
+    },
+    //BSC
+    DexPreset {
+        chain: Chain::Bsc,
+        name: "pancakeswap_v2",
+        factory_address: "0xcA143Ce32Fe78f1f7019d7d551a6402fC5350c73",
+        pool_variant: PoolVariant::UniswapV2,
+        creation_block: 6809737,
+        fee_model: Some(FeeModel::Fixed(25)),
+        init_code_hash: Some(PANCAKESWAP_V2_INIT_CODE_HASH),
+    },
+    DexPreset {
+        chain: Chain::Bsc,
+        name: "biswap",
+        factory_address: "0x858E3312ed3A876947EA49d572A7C42DE08af7EE",
+        pool_variant: PoolVariant::UniswapV2,
+        creation_block: 7664646,
+        //Biswap pairs report their fee in thousandths through `swapFee()`
+        fee_model: Some(FeeModel::PairGetter {
+            function_selector: [0x54, 0xcf, 0x2a, 0xeb],
+            fee_denominator: 1000,
+        }),
+        init_code_hash: None,
+    },
+    DexPreset {
+        chain: Chain::Bsc,
+        name: "uniswap_v3",
+        factory_address: "0xdB1d10011AD0Ff90774D0C6Bb92e5C5c8b4461F7",
+        pool_variant: PoolVariant::UniswapV3,
+        creation_block: 26324014,
+        fee_model: None,
+        init_code_hash: Some(UNISWAP_V3_INIT_CODE_HASH),
+    },
+];
+
+//Gets every dex in the registry for the chain
+pub fn get_dexes(chain: Chain) -> Vec<Dex> {
+    DEX_PRESETS
+        .iter()
+        .filter(|preset| preset.chain == chain)
+        .map(|preset| preset.to_dex())
+        .collect()
+}
+
+//Gets a dex from the registry by name, ie. "uniswap_v3"
+pub fn get_dex(chain: Chain, name: &str) -> Option<Dex> {
+    DEX_PRESETS
+        .iter()
+        .find(|preset| preset.chain == chain && preset.name.eq_ignore_ascii_case(name))
+        .map(|preset| preset.to_dex())
+}
+
+//Gets the dex in the registry deployed at the factory address on any chain
+pub fn get_dex_by_factory(factory_address: H160) -> Option<Dex> {
+    DEX_PRESETS
+        .iter()
+        .find(|preset| H160::from_str(preset.factory_address).unwrap() == factory_address)
+        .map(|preset| preset.to_dex())
+}
+
+//Gets the names of every dex in the registry for the chain
+pub fn get_dex_names(chain: Chain) -> Vec<&'static str> {
+    DEX_PRESETS
+        .iter()
+        .filter(|preset| preset.chain == chain)
+        .map(|preset| preset.name)
+        .collect()
+}
+
+//Gets the chain the provider is connected to
+pub async fn get_provider_chain<P: JsonRpcClient>(
+    provider: Arc<Provider<P>>,
+) -> Result<Chain, PairSyncError<P>> {
+    let chain_id = provider.get_chainid().await?;
+
+    if chain_id > u64::MAX.into() {
+        return Err(PairSyncError::UnsupportedChain(chain_id));
+    }
+
+    Chain::from_chain_id(chain_id.as_u64()).ok_or(PairSyncError::UnsupportedChain(chain_id))
+}
+
+//Checks that the provider is connected to the chain before syncing dexes from the registry
+pub async fn validate_chain<P: JsonRpcClient>(
+    chain: Chain,
+    provider: Arc<Provider<P>>,
+) -> Result<(), PairSyncError<P>> {
+    let chain_id = provider.get_chainid().await?;
+
+    if chain_id != chain.chain_id().into() {
+        return Err(PairSyncError::ChainIdMismatch(chain.chain_id(), chain_id));
+    }
+
+    Ok(())
+}
+
+//Gets the named dexes for the chain the provider is connected to, failing with every name that is not in the registry
+pub async fn get_dexes_for_provider<P: JsonRpcClient>(
+    names: &[&str],
+    provider: Arc<Provider<P>>,
+) -> Result<Vec<Dex>, PairSyncError<P>> {
+    let chain = get_provider_chain(provider).await?;
+
+    let mut dexes = vec![];
+    let mut unknown_dexes = vec![];
+
+    for name in names {
+        match get_dex(chain, name) {
+            Some(dex) => dexes.push(dex),
+            None => unknown_dexes.push(name.to_string()),
+        }
+    }
+
+    if !unknown_dexes.is_empty() {
+        return Err(PairSyncError::UnknownDexes(chain.chain_id(), unknown_dexes));
+    }
+
+    Ok(dexes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAINS: [Chain; 6] = [
+        Chain::Ethereum,
+        Chain::Arbitrum,
+        Chain::Optimism,
+        Chain::Base,
+        Chain::Polygon,
+        Chain::Bsc,
+    ];
+
+    #[test]
+    fn test_build_every_preset() {
+        for preset in DEX_PRESETS {
+            let dex = preset.to_dex();
+
+            assert_eq!(dex.pool_variant, preset.pool_variant);
+            assert!(!dex.factory_address.is_zero(), "{}", preset.name);
+            assert_eq!(
+                dex.init_code_hash.is_some(),
+                preset.init_code_hash.is_some()
+            );
+        }
+
+        for chain in CHAINS {
+            assert_eq!(Chain::from_chain_id(chain.chain_id()), Some(chain));
+            assert!(!chain.wrapped_native_token().is_zero());
+
+            let names = get_dex_names(chain);
+            assert!(!names.is_empty());
+            assert_eq!(get_dexes(chain).len(), names.len());
+
+            //Names are unique within a chain, so every preset can be selected by name
+            for name in names {
+                assert!(get_dex(chain, name).is_some());
+                assert_eq!(
+                    DEX_PRESETS
+                        .iter()
+                        .filter(|preset| preset.chain == chain && preset.name == name)
+                        .count(),
+                    1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_dex_by_factory() {
+        let pancakeswap = get_dex_by_factory(
+            H160::from_str("0xcA143Ce32Fe78f1f7019d7d551a6402fC5350c73").unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(pancakeswap.pool_variant, PoolVariant::UniswapV2);
+        assert!(matches!(pancakeswap.fee_model, FeeModel::Fixed(25)));
+        assert!(get_dex_by_factory(H160::zero()).is_none());
+    }
+
+    #[test]
+    fn test_preset_fee_models() {
+        for preset in DEX_PRESETS {
+            assert_eq!(
+                preset.fee_model.is_some(),
+                preset.pool_variant == PoolVariant::UniswapV2,
+                "{}",
+                preset.name
+            );
+        }
+    }
+}
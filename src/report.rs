@@ -0,0 +1,63 @@
+use ethers::types::H160;
+
+use crate::pool::{Pool, PoolKey};
+
+//Reason a filter removed a pool
+#[derive(Debug, Clone)]
+pub enum ExclusionReason {
+    BlacklistedToken(H160),
+    BlacklistedPool(PoolKey),
+    //The token is not allowlisted. In `AllowlistMode::AnyToken`, none of the pool's tokens are allowlisted.
+    NotAllowlisted(H160),
+    //Value of the pool in the quote token is below the threshold
+    ValueBelowThreshold { value: f64, threshold: f64 },
+    //No pool pairs the token with the base token in the provided dexes, so the pool could not be valued
+    NoPriceRoute(H160),
+    //The pool's token balances are not held separately, ie. UniswapV4 pools, so the pool could not be valued
+    UnknownBalances,
+    TransferTax { token: H160, tax: f64 },
+    Honeypot { token: H160, loss: f64 },
+    //The pool was not deployed by any of the trusted dexes
+    UnverifiedPool,
+    //A call to the node failed while checking the pool
+    RequestError(String),
+    //Removed by a custom filter
+    CustomFilter,
+    //Kept by a filter wrapped in `Not`
+    NegatedFilter,
+}
+
+//Pools removed by one or more filters, along with the reason each pool was removed
+#[derive(Debug, Clone, Default)]
+pub struct FilterReport {
+    pub removed_pools: Vec<(Pool, ExclusionReason)>,
+}
+
+impl FilterReport {
+    pub fn new() -> FilterReport {
+        FilterReport::default()
+    }
+
+    pub fn exclude_pool(&mut self, pool: Pool, reason: ExclusionReason) {
+        self.removed_pools.push((pool, reason));
+    }
+
+    //Gets the reasons the pool was removed, by pool address or by pool id for UniswapV4 pools
+    pub fn get_exclusion_reasons<K: Into<PoolKey>>(&self, pool_key: K) -> Vec<&ExclusionReason> {
+        let pool_key = pool_key.into();
+
+        self.removed_pools
+            .iter()
+            .filter(|(pool, _)| pool.get_pool_key() == pool_key)
+            .map(|(_, reason)| reason)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.removed_pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.removed_pools.is_empty()
+    }
+}
@@ -0,0 +1,738 @@
+use std::{str::FromStr, sync::Arc};
+
+use ethers::{
+    abi::{encode, Token},
+    providers::{
+        call_raw::{spoof, RawCall},
+        JsonRpcClient, Middleware, Provider,
+    },
+    types::{transaction::eip2718::TypedTransaction, Bytes, TransactionRequest, H160, H256, U256},
+};
+
+use crate::{
+    abi,
+    error::{PairSyncError, SwapSimulationError},
+    pool::{is_native_token, Pool, PoolVariant},
+};
+
+//Runtime bytecode of the transfer checker. Called with `(token, recipient, amount, return_transfer)`, it:
+//- transfers `amount` of `token` to `recipient` and measures the recipient's balance change
+//- if `return_transfer` is nonzero, calls the checker at `recipient` to transfer the received amount back to itself
+//- returns `(received, received_back)`
+//Failed or false returning transfers are reported as nothing received instead of reverting.
+//If a `balanceOf` call fails or returns less than a word, ie. the token has no code, nothing is returned.
+//Built from `src/simulation/transfer_checker.py` with `python3 src/simulation/transfer_checker.py`
+const TRANSFER_CHECKER_BYTECODE: &str = "60006080526370a0823160e01b600052602035600452602060806024600060006000355af11560203d10176100de5760805163a9059cbb60e01b600052602035600452604035602452602060006044600060006000355af1156100d7573d1560005117156100d75760006080526370a0823160e01b600052602035600452602060806024600060006000355af11560203d10176100de576080510361010052606035156100d7576000356000523060205261010051604052600060605260406101206080600060006020355af11560403d10176100de575b6040610100f35b60006000f3";
+
+//Address without any token balances that receives the simulated transfer
+const TRANSFER_CHECKER_RECIPIENT: &str = "0x000000000000000000000000000000000000fEe5";
+
+//Runtime bytecode of the round trip simulator. Called with a zero selector and
+//`(pool, token_in, token_out, amount_in, swap_kind, zero_for_one, fee, buy_call, sell_call, amount_offset)`, it:
+//- buys `token_out` with `amount_in` of its own `token_in` balance through the pool, then sells everything received back
+//- returns `(received, received_back, failed_swap)`, where `failed_swap` is 1 if the buy failed, 2 if the sell failed and 0 otherwise
+//Swaps are executed according to the swap kind:
+//- 0: transfers the input to the pair and calls `swap` with the amount out from the reserves and `fee`, in thousandths of a percent
+//- 1: same as 0, with the amount out quoted by the pair's `getAmountOut`
+//- 2: calls `swap` on a concentrated liquidity pool, paying the input in the swap callback
+//- 3: approves the pool and calls it with `buy_call` or `sell_call`, writing the amount in at `amount_offset`
+//Pair swaps use the balance received by the pair as the amount in, so transfer taxes are accounted for.
+//Calls with a nonzero selector are handled as a swap callback `(int256 amount0Delta, int256 amount1Delta, bytes data)`,
+//paying the positive delta in the token encoded in `data`.
+//Built from `src/simulation/round_trip_simulator.py` with `python3 src/simulation/round_trip_simulator.py`
+const ROUND_TRIP_SIMULATOR_BYTECODE: &str = "60003560e01c6100c757600435610200526024356102205260443561024052606435610260526084356102c05260a4356102805260c4356102a052610124356103005260e4356004016102e05260016104405261005a6100e9565b6103c05161040052610220516102405161022052610240526103c05161026052610280511561028052610104356004016102e05260026104405261009c6100e9565b6103c051610420526000610440526060610400f35b61044051156100c1576060610400f35b60006000fd5b6100e76044356024013533600435600081136100e257506024355b61036f565b005b6100f66102405130610341565b610320526102c0518060021461025757806003146102cc575061012361022051610200516102605161036f565b630902f1ac60e01b60005260406101c0600460006000610200515af11560403d10176100b1576101c0516101e0516102805161016657610340526103605261016f565b61036052610340525b61017f6102205161020051610341565b8061034051116100b157610340519003610380526102c0516101c5576102a051620186a00361038051028061036051029061034051620186a0020190046103a052610202565b63f140a35a60e01b600052610380516004526102205160245260206101c0604460006000610200515af11560203d10176100b1576101c0516103a0525b63022c0d9f60e01b6000526103a0516102805161022657600452600060245261022f565b60245260006004525b30604452608060645260006084526000600060a460006000610200515af1156100b157610315565b5063128acb0860e01b600052306004526102805160245261026051604452610280516102975773fffd8963efd1fc6a506488495d951d5263988d2561029e565b6401000276a45b60645260a0608452602060a4526102205160c4526000600060e460006000610200515af1156100b157610315565b506102e1610220516102005161026051610379565b6102e05135806102e051602001610800376102605161030051610800015260006000916108006000610200515af1156100b1575b6103226102405130610341565b6103205181106103375761032051900361033b565b5060005b6103c052565b6370a0823160e01b60005260045260206101c0602460006000855af11560203d10176100b157506101c05190565b63a9059cbb61037f565b63095ea7b35b60e01b60005260245260045260006101c05260206101c0604460006000855af1156100b157503d156103bd573d6020116100b1576101c051156100b1575b56";
+
+//Address the round trip simulator is placed at, its balance of the quote token is set with a state override
+const ROUND_TRIP_SIMULATOR_ADDRESS: &str = "0x0000000000000000000000000000000000005157";
+
+//Balance written to candidate storage slots when searching for the slot holding an account's balance
+const BALANCE_SLOT_PROBE: u64 = 0x5157_5157_5157;
+
+//exchange(int128,int128,uint256,uint256) and exchange(uint256,uint256,uint256,uint256)
+const CURVE_EXCHANGE_SELECTOR: [u8; 4] = [0x3d, 0xf0, 0x21, 0x24];
+const CURVE_EXCHANGE_UINT_SELECTOR: [u8; 4] = [0x5b, 0x41, 0xb9, 0x08];
+//swap((bytes32,uint8,address,address,uint256,bytes),(address,bool,address,bool),uint256,uint256)
+const BALANCER_SWAP_SELECTOR: [u8; 4] = [0x52, 0xbb, 0xbe, 0x29];
+
+//Balancer pools hold no tokens, the balances are held by the vault
+const BALANCER_VAULT_ADDRESS: &str = "0xBA12222222228d8Ba445958a75a0704d566BF2C8";
+
+//Share of the holder's balance that is transferred in the simulation
+const TRANSFER_AMOUNT_DIVISOR: u128 = 1000;
+
+//Tax measured when transferring a token, as a fraction of the amount sent
+#[derive(Debug, Clone, Copy)]
+pub struct TransferTax {
+    //Tax on a transfer out of the pool, ie. a buy
+    pub transfer_in: f64,
+    //Tax on a transfer from a regular holder back into the pool, ie. a sell
+    pub transfer_out: f64,
+}
+
+impl TransferTax {
+    //Gets the tax from the amounts received by the recipient and received back by the sender
+    fn new(amount: U256, received: U256, received_back: U256) -> TransferTax {
+        TransferTax {
+            transfer_in: 1.0 - ratio(received, amount),
+            transfer_out: 1.0 - ratio(received_back, received),
+        }
+    }
+
+    pub fn max_tax(&self) -> f64 {
+        self.transfer_in.max(self.transfer_out)
+    }
+}
+
+//Simulates a transfer of `amount` out of the holder and back with `eth_call`, returning the amount received by the
+//recipient and the amount received back by the holder. The transfer checker is placed at the holder and recipient
+//with a state override, so the provider must support state overrides. Fails with `InvalidSimulationResult` if
+//the token's balances can not be read.
+pub async fn simulate_transfer<P: JsonRpcClient>(
+    token: H160,
+    holder: H160,
+    amount: U256,
+    provider: Arc<Provider<P>>,
+) -> Result<(U256, U256), PairSyncError<P>> {
+    let recipient = H160::from_str(TRANSFER_CHECKER_RECIPIENT).unwrap();
+    let transfer_checker = Bytes::from_str(TRANSFER_CHECKER_BYTECODE).unwrap();
+
+    let mut state = spoof::state();
+    state.account(holder).code(transfer_checker.clone());
+    state.account(recipient).code(transfer_checker);
+
+    let tx: TypedTransaction = TransactionRequest::new()
+        .to(holder)
+        .data(encode(&[
+            Token::Address(token),
+            Token::Address(recipient),
+            Token::Uint(amount),
+            Token::Uint(U256::one()),
+        ]))
+        .into();
+
+    let result = provider.call_raw(&tx).state(&state).await?;
+
+    decode_transfer_result(token, amount, &result.0)
+}
+
+//Decodes the `(received, received_back)` returned by the transfer checker
+fn decode_transfer_result<P: JsonRpcClient>(
+    token: H160,
+    amount: U256,
+    result: &[u8],
+) -> Result<(U256, U256), PairSyncError<P>> {
+    if result.len() < 64 {
+        return Err(PairSyncError::InvalidSimulationResult(token));
+    }
+
+    //The received amounts are capped at the amount sent in case of rebasing balances
+    let received = U256::from_big_endian(&result[0..32]).min(amount);
+    let received_back = U256::from_big_endian(&result[32..64]).min(received);
+
+    Ok((received, received_back))
+}
+
+//Measures the transfer tax of a token by simulating a transfer out of the holder and back.
+//A tax of 1.0 means the transfer failed or nothing was received.
+pub async fn simulate_transfer_tax<P: JsonRpcClient>(
+    token: H160,
+    holder: H160,
+    amount: U256,
+    provider: Arc<Provider<P>>,
+) -> Result<TransferTax, PairSyncError<P>> {
+    let (received, received_back) = simulate_transfer(token, holder, amount, provider).await?;
+
+    Ok(TransferTax::new(amount, received, received_back))
+}
+
+//Measures the transfer tax of a token in a pool, transferring a small share of the pool's balance.
+//Returns None if the pool holds none of the token or the token is native ether, which has no transfer tax.
+pub async fn simulate_pool_transfer_tax<P: JsonRpcClient>(
+    pool: &Pool,
+    token: H160,
+    provider: Arc<Provider<P>>,
+) -> Result<Option<TransferTax>, PairSyncError<P>> {
+    if is_native_token(token) {
+        return Ok(None);
+    }
+
+    let balance = match pool
+        .tokens
+        .iter()
+        .position(|pool_token| *pool_token == token)
+    {
+        Some(index) => pool.balances.get(index).copied().unwrap_or(0),
+        None => return Ok(None),
+    };
+
+    if balance == 0 {
+        return Ok(None);
+    }
+
+    let amount = U256::from((balance / TRANSFER_AMOUNT_DIVISOR).max(1));
+
+    Ok(Some(
+        simulate_transfer_tax(token, get_token_holder(pool), amount, provider).await?,
+    ))
+}
+
+//How the round trip simulator swaps through a pool, see `ROUND_TRIP_SIMULATOR_BYTECODE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SwapKind {
+    Pair = 0,
+    PairQuote = 1,
+    ConcentratedLiquidity = 2,
+    Call = 3,
+}
+
+//A way to swap through a pool, with the calls made for the buy and sell of `SwapKind::Call` swaps
+struct RoundTripSwap {
+    target: H160,
+    swap_kind: SwapKind,
+    buy_call: Vec<u8>,
+    sell_call: Vec<u8>,
+    //Offset in the calls where the amount in is written
+    amount_offset: usize,
+}
+
+//Simulates buying `token` from the pool with a small share of the pool's `quote_token` balance and immediately
+//selling it back, executing both swaps against the pool with `eth_call`. The simulator is funded by overriding
+//its balance of the quote token, so tokens that block sells, tax transfers from the pool or trap the swap are caught.
+//Returns the share of the quote amount lost in the round trip, 1.0 if either swap reverts, or None if the pool holds
+//none of the quote token, either token is native ether or the quote token's balance slot can not be found.
+//UniswapV4 swaps that leave the current tick range can not be quoted, so they also return None.
+pub async fn simulate_round_trip<P: JsonRpcClient>(
+    pool: &Pool,
+    token: H160,
+    quote_token: H160,
+    provider: Arc<Provider<P>>,
+) -> Result<Option<f64>, PairSyncError<P>> {
+    let balance_slot = find_balance_slot(
+        quote_token,
+        H160::from_str(ROUND_TRIP_SIMULATOR_ADDRESS).unwrap(),
+        provider.clone(),
+    )
+    .await?;
+
+    simulate_round_trip_with_balance_slot(pool, token, quote_token, balance_slot, provider).await
+}
+
+//Simulates a round trip through the pool, see `simulate_round_trip`. The balance slot is the storage slot of the
+//quote token holding the simulator's balance, see `find_simulator_balance_slot`.
+pub(crate) async fn simulate_round_trip_with_balance_slot<P: JsonRpcClient>(
+    pool: &Pool,
+    token: H160,
+    quote_token: H160,
+    balance_slot: Option<H256>,
+    provider: Arc<Provider<P>>,
+) -> Result<Option<f64>, PairSyncError<P>> {
+    if is_native_token(token) || is_native_token(quote_token) {
+        return Ok(None);
+    }
+
+    let quote_balance = match pool
+        .tokens
+        .iter()
+        .position(|pool_token| *pool_token == quote_token)
+    {
+        Some(index) => pool.balances.get(index).copied().unwrap_or(0),
+        None => return Ok(None),
+    };
+
+    if quote_balance == 0 {
+        return Ok(None);
+    }
+
+    let quote_amount_in = U256::from((quote_balance / TRANSFER_AMOUNT_DIVISOR).max(1));
+
+    let swaps = get_round_trip_swaps(pool, token, quote_token);
+
+    //Pools that can not be swapped directly, ie. UniswapV4 pools behind the PoolManager's lock, only have their token transfers simulated
+    if swaps.is_empty() {
+        return simulate_transfer_round_trip(pool, token, quote_token, quote_amount_in, provider)
+            .await;
+    }
+
+    let balance_slot = match balance_slot {
+        Some(balance_slot) => balance_slot,
+        None => return Ok(None),
+    };
+
+    let simulator = H160::from_str(ROUND_TRIP_SIMULATOR_ADDRESS).unwrap();
+    let mut amount_bytes = [0u8; 32];
+    quote_amount_in.to_big_endian(&mut amount_bytes);
+
+    let mut state = spoof::state();
+    state
+        .account(simulator)
+        .code(Bytes::from_str(ROUND_TRIP_SIMULATOR_BYTECODE).unwrap());
+    state
+        .account(quote_token)
+        .store(balance_slot, H256::from(amount_bytes));
+
+    let zero_for_one = pool.tokens.first() == Some(&quote_token);
+
+    //Swaps are tried in order until the buy succeeds, ie. Curve pools with int128 and uint256 coin indices
+    for swap in swaps {
+        let data = encode_round_trip_call(
+            swap,
+            token,
+            quote_token,
+            quote_amount_in,
+            zero_for_one,
+            pool.fee,
+        );
+
+        let tx: TypedTransaction = TransactionRequest::new().to(simulator).data(data).into();
+        let result = provider.call_raw(&tx).state(&state).await?;
+
+        if result.0.len() < 96 {
+            return Err(PairSyncError::InvalidSimulationResult(token));
+        }
+
+        let received_back = U256::from_big_endian(&result.0[32..64]);
+        let failed_swap = U256::from_big_endian(&result.0[64..96]);
+
+        if failed_swap.is_zero() {
+            return Ok(Some(1.0 - ratio(received_back, quote_amount_in).min(1.0)));
+        }
+
+        //A failed sell is a honeypot, a failed buy may be a swap the pool does not support
+        if failed_swap == U256::from(2) {
+            return Ok(Some(1.0));
+        }
+    }
+
+    Ok(Some(1.0))
+}
+
+//Encodes the round trip simulator call buying `token` with `quote_amount_in` of `quote_token` through the swap
+fn encode_round_trip_call(
+    swap: RoundTripSwap,
+    token: H160,
+    quote_token: H160,
+    quote_amount_in: U256,
+    zero_for_one: bool,
+    fee: u32,
+) -> Vec<u8> {
+    let mut data = vec![0u8; 4];
+    data.extend(encode(&[
+        Token::Address(swap.target),
+        Token::Address(quote_token),
+        Token::Address(token),
+        Token::Uint(quote_amount_in),
+        Token::Uint(U256::from(swap.swap_kind as u8)),
+        Token::Bool(zero_for_one),
+        Token::Uint(U256::from(fee)),
+        Token::Bytes(swap.buy_call),
+        Token::Bytes(swap.sell_call),
+        Token::Uint(U256::from(swap.amount_offset)),
+    ]));
+    data
+}
+
+//Gets the ways to swap between the tokens through the pool, empty if the simulator can not swap through the pool
+fn get_round_trip_swaps(pool: &Pool, token: H160, quote_token: H160) -> Vec<RoundTripSwap> {
+    let pair_swap = |swap_kind| RoundTripSwap {
+        target: pool.address,
+        swap_kind,
+        buy_call: vec![],
+        sell_call: vec![],
+        amount_offset: 0,
+    };
+
+    match pool.pool_variant {
+        PoolVariant::UniswapV2 => vec![pair_swap(SwapKind::Pair)],
+        //Solidly pairs quote their own amount out, which covers the stable curve
+        PoolVariant::Solidly => vec![pair_swap(SwapKind::PairQuote)],
+        PoolVariant::UniswapV3 | PoolVariant::Algebra => {
+            vec![pair_swap(SwapKind::ConcentratedLiquidity)]
+        }
+
+        PoolVariant::Curve => {
+            let coin_index = |coin| {
+                pool.tokens
+                    .iter()
+                    .position(|pool_token| *pool_token == coin)
+                    .unwrap_or_default()
+            };
+            let (token_index, quote_index) = (coin_index(token), coin_index(quote_token));
+
+            //exchange(i, j, dx, min_dy), the amount in is written at dx
+            let exchange = |selector: [u8; 4], i: usize, j: usize| {
+                let mut call = selector.to_vec();
+                call.extend(encode(&[
+                    Token::Uint(U256::from(i)),
+                    Token::Uint(U256::from(j)),
+                    Token::Uint(U256::zero()),
+                    Token::Uint(U256::zero()),
+                ]));
+                call
+            };
+
+            [CURVE_EXCHANGE_SELECTOR, CURVE_EXCHANGE_UINT_SELECTOR]
+                .into_iter()
+                .map(|selector| RoundTripSwap {
+                    target: pool.address,
+                    swap_kind: SwapKind::Call,
+                    buy_call: exchange(selector, quote_index, token_index),
+                    sell_call: exchange(selector, token_index, quote_index),
+                    amount_offset: 68,
+                })
+                .collect()
+        }
+
+        PoolVariant::BalancerV2 => {
+            let simulator = H160::from_str(ROUND_TRIP_SIMULATOR_ADDRESS).unwrap();
+
+            //Vault swap with a given in single swap, the amount in is written at singleSwap.amount
+            let vault_swap = |asset_in: H160, asset_out: H160| {
+                let mut call = BALANCER_SWAP_SELECTOR.to_vec();
+                call.extend(encode(&[
+                    Token::Tuple(vec![
+                        Token::FixedBytes(pool.pool_id.as_bytes().to_vec()),
+                        Token::Uint(U256::zero()),
+                        Token::Address(asset_in),
+                        Token::Address(asset_out),
+                        Token::Uint(U256::zero()),
+                        Token::Bytes(vec![]),
+                    ]),
+                    Token::Tuple(vec![
+                        Token::Address(simulator),
+                        Token::Bool(false),
+                        Token::Address(simulator),
+                        Token::Bool(false),
+                    ]),
+                    Token::Uint(U256::zero()),
+                    Token::Uint(U256::MAX),
+                ]));
+                call
+            };
+
+            vec![RoundTripSwap {
+                target: get_token_holder(pool),
+                swap_kind: SwapKind::Call,
+                buy_call: vault_swap(quote_token, token),
+                sell_call: vault_swap(token, quote_token),
+                amount_offset: 356,
+            }]
+        }
+
+        PoolVariant::UniswapV4 => vec![],
+    }
+}
+
+//Simulates the token transfers of a round trip, with the swap amounts computed from the pool's state
+async fn simulate_transfer_round_trip<P: JsonRpcClient>(
+    pool: &Pool,
+    token: H160,
+    quote_token: H160,
+    quote_amount_in: U256,
+    provider: Arc<Provider<P>>,
+) -> Result<Option<f64>, PairSyncError<P>> {
+    //Pools that can not quote the swap have no liquidity to sell into, swaps past the current tick can not be quoted
+    let token_amount_out = match pool.get_amount_out(quote_token, token, quote_amount_in) {
+        Ok(amount_out) if !amount_out.is_zero() => amount_out,
+        Err(SwapSimulationError::TickRangeExceeded) => return Ok(None),
+        _ => return Ok(Some(1.0)),
+    };
+
+    let (_, token_amount_sold) =
+        simulate_transfer(token, get_token_holder(pool), token_amount_out, provider).await?;
+
+    let quote_amount_out = match pool.get_amount_out(token, quote_token, token_amount_sold) {
+        Ok(amount_out) => amount_out,
+        Err(SwapSimulationError::TickRangeExceeded) => return Ok(None),
+        Err(_) => U256::zero(),
+    };
+
+    Ok(Some(
+        1.0 - ratio(quote_amount_out, quote_amount_in).min(1.0),
+    ))
+}
+
+//Finds the storage slot of the token holding the account's balance, so the balance can be set with a state override.
+//Candidate slots are the slots of the token read by `balanceOf`, found with `eth_createAccessList`, and a slot is
+//confirmed if overriding it changes the balance. Returns None for tokens where no single slot holds the balance.
+pub async fn find_balance_slot<P: JsonRpcClient>(
+    token: H160,
+    account: H160,
+    provider: Arc<Provider<P>>,
+) -> Result<Option<H256>, PairSyncError<P>> {
+    let balance_of = abi::IErc20::new(token, provider.clone()).balance_of(account);
+
+    let access_list = provider.create_access_list(&balance_of.tx, None).await?;
+
+    let probe = U256::from(BALANCE_SLOT_PROBE);
+    let mut probe_bytes = [0u8; 32];
+    probe.to_big_endian(&mut probe_bytes);
+
+    for access_list_item in access_list.access_list.0 {
+        if access_list_item.address != token {
+            continue;
+        }
+
+        for storage_key in access_list_item.storage_keys {
+            let mut state = spoof::state();
+            state
+                .account(token)
+                .store(storage_key, H256::from(probe_bytes));
+
+            let result = provider.call_raw(&balance_of.tx).state(&state).await?;
+            if result.0.len() >= 32 && U256::from_big_endian(&result.0[0..32]) == probe {
+                return Ok(Some(storage_key));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+//Finds the storage slot of the token holding the round trip simulator's balance, see `find_balance_slot`
+pub(crate) async fn find_simulator_balance_slot<P: JsonRpcClient>(
+    token: H160,
+    provider: Arc<Provider<P>>,
+) -> Result<Option<H256>, PairSyncError<P>> {
+    find_balance_slot(
+        token,
+        H160::from_str(ROUND_TRIP_SIMULATOR_ADDRESS).unwrap(),
+        provider,
+    )
+    .await
+}
+
+//Gets the address holding the pool's token balances
+fn get_token_holder(pool: &Pool) -> H160 {
+    match pool.pool_variant {
+        PoolVariant::BalancerV2 => H160::from_str(BALANCER_VAULT_ADDRESS).unwrap(),
+        _ => pool.address,
+    }
+}
+
+fn ratio(numerator: U256, denominator: U256) -> f64 {
+    if denominator.is_zero() {
+        return 0.0;
+    }
+
+    //Scale to six decimals of precision before converting to a float
+    (numerator * U256::from(1_000_000u64) / denominator).as_u64() as f64 / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        abi::{decode, ParamType},
+        providers::Http,
+    };
+
+    use super::*;
+
+    fn transfer_result(received: u64, received_back: u64) -> Vec<u8> {
+        encode(&[
+            Token::Uint(U256::from(received)),
+            Token::Uint(U256::from(received_back)),
+        ])
+    }
+
+    fn decode_transfer_tax(amount: u64, result: &[u8]) -> Result<TransferTax, PairSyncError<Http>> {
+        let amount = U256::from(amount);
+        let (received, received_back) = decode_transfer_result(H160::zero(), amount, result)?;
+        Ok(TransferTax::new(amount, received, received_back))
+    }
+
+    #[test]
+    fn test_decode_transfer_tax() {
+        //No tax
+        let tax = decode_transfer_tax(1000, &transfer_result(1000, 1000)).unwrap();
+        assert_eq!((tax.transfer_in, tax.transfer_out), (0.0, 0.0));
+
+        //25% on buys and 50% on sells
+        let tax = decode_transfer_tax(1000, &transfer_result(750, 375)).unwrap();
+        assert_eq!((tax.transfer_in, tax.transfer_out), (0.25, 0.5));
+        assert_eq!(tax.max_tax(), 0.5);
+
+        //A failed transfer receives nothing
+        let tax = decode_transfer_tax(1000, &transfer_result(0, 0)).unwrap();
+        assert_eq!((tax.transfer_in, tax.transfer_out), (1.0, 1.0));
+
+        //Rebasing balances are capped at the amount sent
+        let tax = decode_transfer_tax(1000, &transfer_result(1010, 1020)).unwrap();
+        assert_eq!((tax.transfer_in, tax.transfer_out), (0.0, 0.0));
+
+        //Trailing data is ignored
+        let mut result = transfer_result(1000, 500);
+        result.extend([0u8; 32]);
+        let tax = decode_transfer_tax(1000, &result).unwrap();
+        assert_eq!((tax.transfer_in, tax.transfer_out), (0.0, 0.5));
+    }
+
+    #[test]
+    fn test_decode_invalid_transfer_result() {
+        let token = H160::repeat_byte(1);
+
+        //The checker returns nothing if the token's balances can not be read
+        for result in [vec![], vec![0u8; 32], vec![0u8; 63]] {
+            assert!(matches!(
+                decode_transfer_result::<Http>(token, U256::from(1000), &result),
+                Err(PairSyncError::InvalidSimulationResult(invalid_token)) if invalid_token == token
+            ));
+        }
+    }
+
+    //Writes the amount in at the offset of the call, as the simulator does for `SwapKind::Call` swaps
+    fn patch_amount(call: &[u8], amount_offset: usize, amount: U256) -> Vec<u8> {
+        let mut call = call.to_vec();
+        amount.to_big_endian(&mut call[amount_offset..amount_offset + 32]);
+        call
+    }
+
+    #[test]
+    fn test_pair_round_trip_swaps() {
+        let (token, quote_token) = (H160::repeat_byte(1), H160::repeat_byte(2));
+        let amount = U256::from(123_456_789u64);
+
+        for (pool_variant, swap_kind) in [
+            (PoolVariant::UniswapV2, SwapKind::Pair),
+            (PoolVariant::Solidly, SwapKind::PairQuote),
+            (PoolVariant::UniswapV3, SwapKind::ConcentratedLiquidity),
+            (PoolVariant::Algebra, SwapKind::ConcentratedLiquidity),
+        ] {
+            let pool = Pool::new(
+                H160::repeat_byte(3),
+                quote_token,
+                18,
+                token,
+                18,
+                true,
+                0,
+                0,
+                3000,
+                pool_variant,
+            );
+
+            let mut swaps = get_round_trip_swaps(&pool, token, quote_token);
+            assert_eq!(swaps.len(), 1);
+            let swap = swaps.remove(0);
+            assert_eq!(swap.swap_kind, swap_kind);
+            assert_eq!(swap.target, pool.address);
+            assert!(swap.buy_call.is_empty() && swap.sell_call.is_empty());
+
+            //Zero selector followed by the arguments, one word each
+            let data = encode_round_trip_call(swap, token, quote_token, amount, true, pool.fee);
+            let word = |index: usize| U256::from_big_endian(&data[4 + 32 * index..36 + 32 * index]);
+            assert_eq!(data[0..4], [0u8; 4]);
+            assert_eq!(word(0), U256::from(pool.address.as_bytes()));
+            assert_eq!(word(3), amount);
+            assert_eq!(word(4), U256::from(swap_kind as u8));
+            assert_eq!(word(5), U256::one());
+            assert_eq!(word(6), U256::from(3000));
+        }
+
+        let pool = Pool::empty_pool(PoolVariant::UniswapV4);
+        assert!(get_round_trip_swaps(&pool, token, quote_token).is_empty());
+    }
+
+    #[test]
+    fn test_curve_round_trip_swaps() {
+        let (token, quote_token) = (H160::repeat_byte(1), H160::repeat_byte(2));
+        let amount = U256::from(123_456_789u64);
+
+        let mut pool = Pool::new_curve_pool(H160::repeat_byte(3));
+        pool.tokens = vec![H160::repeat_byte(4), quote_token, token];
+
+        let swaps = get_round_trip_swaps(&pool, token, quote_token);
+        assert_eq!(swaps.len(), 2);
+
+        for (swap, selector) in swaps
+            .iter()
+            .zip([CURVE_EXCHANGE_SELECTOR, CURVE_EXCHANGE_UINT_SELECTOR])
+        {
+            assert_eq!(swap.swap_kind, SwapKind::Call);
+            assert_eq!(swap.target, pool.address);
+            assert_eq!(swap.amount_offset, 68);
+
+            //exchange(i, j, dx, min_dy) with the amount in at dx
+            for (call, i, j) in [(&swap.buy_call, 1, 2), (&swap.sell_call, 2, 1)] {
+                let call = patch_amount(call, swap.amount_offset, amount);
+                assert_eq!(call[0..4], selector);
+                assert_eq!(
+                    decode(&vec![ParamType::Uint(256); 4], &call[4..]).unwrap(),
+                    vec![
+                        Token::Uint(U256::from(i)),
+                        Token::Uint(U256::from(j)),
+                        Token::Uint(amount),
+                        Token::Uint(U256::zero()),
+                    ]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_balancer_round_trip_swaps() {
+        let (token, quote_token) = (H160::repeat_byte(1), H160::repeat_byte(2));
+        let amount = U256::from(123_456_789u64);
+        let simulator = H160::from_str(ROUND_TRIP_SIMULATOR_ADDRESS).unwrap();
+
+        let mut pool = Pool::empty_pool(PoolVariant::BalancerV2);
+        pool.address = H160::repeat_byte(3);
+        pool.pool_id = H256::repeat_byte(5);
+        pool.tokens = vec![quote_token, token];
+
+        let mut swaps = get_round_trip_swaps(&pool, token, quote_token);
+        assert_eq!(swaps.len(), 1);
+        let swap = swaps.remove(0);
+        assert_eq!(swap.swap_kind, SwapKind::Call);
+        assert_eq!(swap.target, H160::from_str(BALANCER_VAULT_ADDRESS).unwrap());
+        assert_eq!(swap.amount_offset, 356);
+
+        //swap(singleSwap, funds, limit, deadline) with the amount in at singleSwap.amount
+        let swap_params = [
+            ParamType::Tuple(vec![
+                ParamType::FixedBytes(32),
+                ParamType::Uint(8),
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Bytes,
+            ]),
+            ParamType::Tuple(vec![
+                ParamType::Address,
+                ParamType::Bool,
+                ParamType::Address,
+                ParamType::Bool,
+            ]),
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+        ];
+
+        for (call, asset_in, asset_out) in [
+            (&swap.buy_call, quote_token, token),
+            (&swap.sell_call, token, quote_token),
+        ] {
+            let call = patch_amount(call, swap.amount_offset, amount);
+            assert_eq!(call[0..4], BALANCER_SWAP_SELECTOR);
+            assert_eq!(
+                decode(&swap_params, &call[4..]).unwrap(),
+                vec![
+                    Token::Tuple(vec![
+                        Token::FixedBytes(pool.pool_id.as_bytes().to_vec()),
+                        Token::Uint(U256::zero()),
+                        Token::Address(asset_in),
+                        Token::Address(asset_out),
+                        Token::Uint(amount),
+                        Token::Bytes(vec![]),
+                    ]),
+                    Token::Tuple(vec![
+                        Token::Address(simulator),
+                        Token::Bool(false),
+                        Token::Address(simulator),
+                        Token::Bool(false),
+                    ]),
+                    Token::Uint(U256::zero()),
+                    Token::Uint(U256::MAX),
+                ]
+            );
+        }
+    }
+}
@@ -0,0 +1,60 @@
+#Minimal EVM assembler with labels used to build the simulation contracts in simulation.rs.
+#A program is a list of items:
+#  'OPNAME'             opcode
+#  int                  push with the smallest width
+#  ('push', n, width)   push with a fixed width
+#  ('label', name)      JUMPDEST with a label
+#  ('ref', name)        PUSH2 of a label
+OPS = {
+    'STOP': 0x00, 'ADD': 0x01, 'MUL': 0x02, 'SUB': 0x03, 'DIV': 0x04,
+    'LT': 0x10, 'GT': 0x11, 'SLT': 0x12, 'SGT': 0x13, 'EQ': 0x14, 'ISZERO': 0x15,
+    'AND': 0x16, 'OR': 0x17, 'SHL': 0x1b, 'SHR': 0x1c,
+    'ADDRESS': 0x30, 'CALLER': 0x33, 'CALLDATALOAD': 0x35, 'CALLDATASIZE': 0x36, 'CALLDATACOPY': 0x37,
+    'RETURNDATASIZE': 0x3d,
+    'POP': 0x50, 'MLOAD': 0x51, 'MSTORE': 0x52, 'JUMP': 0x56, 'JUMPI': 0x57, 'GAS': 0x5a, 'JUMPDEST': 0x5b,
+    'CALL': 0xf1, 'RETURN': 0xf3, 'REVERT': 0xfd,
+}
+for i in range(1, 17):
+    OPS['DUP%d' % i] = 0x7f + i
+    OPS['SWAP%d' % i] = 0x8f + i
+
+
+def assemble(program):
+    def size(item):
+        if isinstance(item, str):
+            return 1
+        if isinstance(item, int):
+            return 1 + max(1, (item.bit_length() + 7) // 8)
+        if item[0] == 'push':
+            return 1 + item[2]
+        if item[0] == 'label':
+            return 1
+        if item[0] == 'ref':
+            return 3
+        raise ValueError(item)
+
+    labels = {}
+    pc = 0
+    for item in program:
+        if isinstance(item, tuple) and item[0] == 'label':
+            assert item[1] not in labels, item
+            labels[item[1]] = pc
+        pc += size(item)
+
+    out = bytearray()
+    for item in program:
+        if isinstance(item, str):
+            out.append(OPS[item])
+        elif isinstance(item, int):
+            width = max(1, (item.bit_length() + 7) // 8)
+            out.append(0x5f + width)
+            out += item.to_bytes(width, 'big')
+        elif item[0] == 'push':
+            out.append(0x5f + item[2])
+            out += item[1].to_bytes(item[2], 'big')
+        elif item[0] == 'label':
+            out.append(0x5b)
+        elif item[0] == 'ref':
+            out.append(0x61)
+            out += labels[item[1]].to_bytes(2, 'big')
+    return bytes(out)
@@ -0,0 +1,161 @@
+#Source of `ROUND_TRIP_SIMULATOR_BYTECODE` in simulation.rs, prints the runtime bytecode.
+#Build with `python3 src/simulation/round_trip_simulator.py`
+from asm import assemble
+
+SEL_BALANCE_OF = 0x70a08231
+SEL_TRANSFER = 0xa9059cbb
+SEL_APPROVE = 0x095ea7b3
+SEL_GET_RESERVES = 0x0902f1ac
+SEL_GET_AMOUNT_OUT = 0xf140a35a
+SEL_V2_SWAP = 0x022c0d9f
+SEL_V3_SWAP = 0x128acb08
+
+MIN_SQRT_RATIO_PLUS_ONE = 4295128739 + 1
+MAX_SQRT_RATIO_MINUS_ONE = 1461446703485210103287273052203988822378723970342 - 1
+
+
+def selector(sel):
+    return [('push', sel, 4), 0xe0, 'SHL', 0, 'MSTORE']
+
+
+#Memory layout of the round trip simulator
+POOL, TIN, TOUT, AMT, ZFO, FEE, KIND, TMPL, PATCH, BEFORE, RIN, ROUT, AIN, AOUT, LEGRES = [
+    0x200 + 0x20 * i for i in range(15)]
+RES = 0x400
+STATUS = 0x440
+RET = 0x1c0
+TEMPLATE = 0x800
+
+
+def call_sub(name, ret, args):
+    return [('ref', ret)] + args + [('ref', name), 'JUMP', ('label', ret)]
+
+
+def mload(off):
+    return [('push', off, 2), 'MLOAD']
+
+
+def mstore(off):
+    return [('push', off, 2), 'MSTORE']
+
+
+#Round trip simulator, see simulation.rs for the calldata layout
+def round_trip_simulator():
+    p = []
+    #Dispatch, calls with a nonzero selector are swap callbacks
+    p += [0, 'CALLDATALOAD', 0xe0, 'SHR', ('ref', 'callback'), 'JUMPI']
+
+    #Buy leg
+    for i, off in [(0, POOL), (1, TIN), (2, TOUT), (3, AMT), (4, KIND), (5, ZFO), (6, FEE), (9, PATCH)]:
+        p += [4 + 0x20 * i, 'CALLDATALOAD'] + mstore(off)
+    p += [4 + 0x20 * 7, 'CALLDATALOAD', 4, 'ADD'] + mstore(TMPL)
+    p += [1] + mstore(STATUS)
+    p += call_sub('leg', 'buy_done', [])
+    p += mload(LEGRES) + mstore(RES)
+
+    #Sell leg, selling the received tokens back into the pool
+    p += mload(TIN) + mload(TOUT) + mstore(TIN) + mstore(TOUT)
+    p += mload(LEGRES) + mstore(AMT)
+    p += mload(ZFO) + ['ISZERO'] + mstore(ZFO)
+    p += [4 + 0x20 * 8, 'CALLDATALOAD', 4, 'ADD'] + mstore(TMPL)
+    p += [2] + mstore(STATUS)
+    p += call_sub('leg', 'sell_done', [])
+    p += mload(LEGRES) + mstore(RES + 0x20)
+    p += [0] + mstore(STATUS)
+    p += [0x60, ('push', RES, 2), 'RETURN']
+
+    #Failure, returns the results so far from the main call and reverts from a callback
+    p += [('label', 'fail')] + mload(STATUS) + ['ISZERO', ('ref', 'revert'), 'JUMPI',
+                                                 0x60, ('push', RES, 2), 'RETURN',
+                                                 ('label', 'revert'), 0, 0, 'REVERT']
+
+    #Swap callback (int256 amount0Delta, int256 amount1Delta, bytes data), pays the positive delta of the token in data
+    p += [('label', 'callback'), ('ref', 'callback_done'),
+          0x44, 'CALLDATALOAD', 0x24, 'ADD', 'CALLDATALOAD', 'CALLER',
+          4, 'CALLDATALOAD', 0, 'DUP2', 'SGT', ('ref', 'callback_paid'), 'JUMPI',
+          'POP', 0x24, 'CALLDATALOAD',
+          ('label', 'callback_paid'), ('ref', 'transfer'), 'JUMP',
+          ('label', 'callback_done'), 'STOP']
+
+    #Leg: swaps AMT of TIN for TOUT in POOL and stores the amount of TOUT received in LEGRES. Stack: [ret]
+    p += [('label', 'leg')]
+    p += call_sub('balance_of', 'leg_before', mload(TOUT) + ['ADDRESS']) + mstore(BEFORE)
+    p += mload(KIND) + ['DUP1', 2, 'EQ', ('ref', 'v3'), 'JUMPI',
+                        'DUP1', 3, 'EQ', ('ref', 'call_template'), 'JUMPI', 'POP']
+
+    #Pair swaps, the input is transferred to the pair before calling swap with the amount out
+    p += call_sub('transfer', 'v2_transferred', mload(TIN) + mload(POOL) + mload(AMT))
+    p += selector(SEL_GET_RESERVES)
+    p += [0x40, ('push', RET, 2), 4, 0, 0] + mload(POOL) + ['GAS', 'CALL',
+                                                           'ISZERO', 0x40, 'RETURNDATASIZE', 'LT', 'OR', ('ref', 'fail'), 'JUMPI']
+    p += mload(RET) + mload(RET + 0x20) + mload(ZFO) + [('ref', 'zfo_reserves'), 'JUMPI']
+    p += mstore(RIN) + mstore(ROUT) + [('ref', 'reserves_done'), 'JUMP']
+    p += [('label', 'zfo_reserves')] + mstore(ROUT) + mstore(RIN) + [('label', 'reserves_done')]
+    #The amount in is the balance received by the pair, which accounts for transfer taxes
+    p += call_sub('balance_of', 'v2_balance', mload(TIN) + mload(POOL))
+    p += ['DUP1'] + mload(RIN) + ['GT', ('ref', 'fail'), 'JUMPI']
+    p += mload(RIN) + ['SWAP1', 'SUB'] + mstore(AIN)
+    p += mload(KIND) + [('ref', 'pair_amount_out'), 'JUMPI']
+    #Constant product amount out with the fee in thousandths of a percent
+    p += mload(FEE) + [('push', 100000, 3), 'SUB'] + mload(AIN) + ['MUL',
+          'DUP1'] + mload(ROUT) + ['MUL', 'SWAP1'] + mload(RIN) + [('push', 100000, 3), 'MUL', 'ADD',
+          'SWAP1', 'DIV'] + mstore(AOUT) + [('ref', 'v2_swap'), 'JUMP']
+    #Pairs that quote their own amount out, ie. Solidly stable pairs
+    p += [('label', 'pair_amount_out')] + selector(SEL_GET_AMOUNT_OUT)
+    p += mload(AIN) + [4, 'MSTORE'] + mload(TIN) + [0x24, 'MSTORE']
+    p += [0x20, ('push', RET, 2), 0x44, 0, 0] + mload(POOL) + ['GAS', 'CALL',
+                                                              'ISZERO', 0x20, 'RETURNDATASIZE', 'LT', 'OR', ('ref', 'fail'), 'JUMPI']
+    p += mload(RET) + mstore(AOUT)
+    #swap(amount0Out, amount1Out, to, data)
+    p += [('label', 'v2_swap')] + selector(SEL_V2_SWAP)
+    p += mload(AOUT) + mload(ZFO) + [('ref', 'zfo_swap'), 'JUMPI',
+                                     4, 'MSTORE', 0, 0x24, 'MSTORE', ('ref', 'swap_amounts_done'), 'JUMP',
+                                     ('label', 'zfo_swap'), 0x24, 'MSTORE', 0, 4, 'MSTORE',
+                                     ('label', 'swap_amounts_done'),
+                                     'ADDRESS', 0x44, 'MSTORE', 0x80, 0x64, 'MSTORE', 0, 0x84, 'MSTORE',
+                                     0, 0, 0xa4, 0, 0] + mload(POOL) + ['GAS', 'CALL', 'ISZERO', ('ref', 'fail'), 'JUMPI',
+                                                                         ('ref', 'measure'), 'JUMP']
+
+    #Concentrated liquidity swaps, the input is paid in the swap callback
+    p += [('label', 'v3'), 'POP'] + selector(SEL_V3_SWAP)
+    p += ['ADDRESS', 4, 'MSTORE'] + mload(ZFO) + [0x24, 'MSTORE'] + mload(AMT) + [0x44, 'MSTORE']
+    p += mload(ZFO) + [('ref', 'zfo_limit'), 'JUMPI', ('push', MAX_SQRT_RATIO_MINUS_ONE, 20), ('ref', 'limit_done'), 'JUMP',
+                       ('label', 'zfo_limit'), ('push', MIN_SQRT_RATIO_PLUS_ONE, 5), ('label', 'limit_done'),
+                       0x64, 'MSTORE', 0xa0, 0x84, 'MSTORE', 0x20, 0xa4, 'MSTORE'] + mload(TIN) + [0xc4, 'MSTORE']
+    p += [0, 0, 0xe4, 0, 0] + mload(POOL) + ['GAS', 'CALL', 'ISZERO', ('ref', 'fail'), 'JUMPI', ('ref', 'measure'), 'JUMP']
+
+    #Approve the pool, then call it with the leg's template, writing the amount in at the patch offset
+    p += [('label', 'call_template'), 'POP']
+    p += call_sub('approve', 'approved', mload(TIN) + mload(POOL) + mload(AMT))
+    p += mload(TMPL) + ['CALLDATALOAD', 'DUP1'] + mload(TMPL) + [0x20, 'ADD', ('push', TEMPLATE, 2), 'CALLDATACOPY']
+    p += mload(AMT) + mload(PATCH) + [('push', TEMPLATE, 2), 'ADD', 'MSTORE']
+    p += [0, 0, 'SWAP2', ('push', TEMPLATE, 2), 0] + mload(POOL) + ['GAS', 'CALL', 'ISZERO', ('ref', 'fail'), 'JUMPI']
+
+    #Amount of TOUT received by the simulator
+    p += [('label', 'measure')]
+    p += call_sub('balance_of', 'leg_after', mload(TOUT) + ['ADDRESS'])
+    p += mload(BEFORE) + ['DUP2', 'LT', ('ref', 'no_gain'), 'JUMPI']
+    p += mload(BEFORE) + ['SWAP1', 'SUB', ('ref', 'leg_done'), 'JUMP']
+    p += [('label', 'no_gain'), 'POP', 0, ('label', 'leg_done')] + mstore(LEGRES) + ['JUMP']
+
+    #balance_of: [ret, token, account] -> [balance], fails on a failed call or a short return
+    p += [('label', 'balance_of')] + selector(SEL_BALANCE_OF) + [4, 'MSTORE',
+          0x20, ('push', RET, 2), 0x24, 0, 0, 'DUP6', 'GAS', 'CALL',
+          'ISZERO', 0x20, 'RETURNDATASIZE', 'LT', 'OR', ('ref', 'fail'), 'JUMPI',
+          'POP'] + mload(RET) + ['SWAP1', 'JUMP']
+
+    #transfer / approve: [ret, token, to, amount] -> [], fails on a failed call or a false return
+    p += [('label', 'transfer'), ('push', SEL_TRANSFER, 4), ('ref', 'erc20_call'), 'JUMP']
+    p += [('label', 'approve'), ('push', SEL_APPROVE, 4)]
+    p += [('label', 'erc20_call'), 0xe0, 'SHL', 0, 'MSTORE', 0x24, 'MSTORE', 4, 'MSTORE',
+          0, ('push', RET, 2), 'MSTORE',
+          0x20, ('push', RET, 2), 0x44, 0, 0, 'DUP6', 'GAS', 'CALL',
+          'ISZERO', ('ref', 'fail'), 'JUMPI', 'POP',
+          'RETURNDATASIZE', 'ISZERO', ('ref', 'erc20_ok'), 'JUMPI',
+          'RETURNDATASIZE', 0x20, 'GT', ('ref', 'fail'), 'JUMPI'] + mload(RET) + ['ISZERO', ('ref', 'fail'), 'JUMPI',
+          ('label', 'erc20_ok'), 'JUMP']
+    return assemble(p)
+
+
+if __name__ == '__main__':
+    print(round_trip_simulator().hex())
@@ -0,0 +1,47 @@
+#Source of `TRANSFER_CHECKER_BYTECODE` in simulation.rs, prints the runtime bytecode.
+#Build with `python3 src/simulation/transfer_checker.py`
+from asm import assemble
+
+SEL_BALANCE_OF = 0x70a08231
+SEL_TRANSFER = 0xa9059cbb
+
+
+def selector(sel):
+    return [('push', sel, 4), 0xe0, 'SHL', 0, 'MSTORE']
+
+
+#Transfer checker: calldata (token, recipient, amount, return_transfer), returns (received, received_back).
+#Returns empty data if a balanceOf call fails or returns less than a word.
+def transfer_checker():
+    def balance_of_recipient():
+        #mstore(0x80, 0); balanceOf(recipient) into 0x80, empty return -> bad
+        return [0, 0x80, 'MSTORE'] + selector(SEL_BALANCE_OF) + [
+            0x20, 'CALLDATALOAD', 4, 'MSTORE',
+            0x20, 0x80, 0x24, 0, 0, 0, 'CALLDATALOAD', 'GAS', 'CALL',
+            'ISZERO', 0x20, 'RETURNDATASIZE', 'LT', 'OR', ('ref', 'bad'), 'JUMPI',
+        ]
+
+    return assemble(
+        balance_of_recipient()
+        + [0x80, 'MLOAD']  #before
+        + selector(SEL_TRANSFER)
+        + [0x20, 'CALLDATALOAD', 4, 'MSTORE', 0x40, 'CALLDATALOAD', 0x24, 'MSTORE',
+           0x20, 0, 0x44, 0, 0, 0, 'CALLDATALOAD', 'GAS', 'CALL',
+           'ISZERO', ('ref', 'done'), 'JUMPI',
+           #false returning transfers are treated as failed
+           'RETURNDATASIZE', 'ISZERO', 0, 'MLOAD', 'OR', 'ISZERO', ('ref', 'done'), 'JUMPI']
+        + balance_of_recipient()
+        + [0x80, 'MLOAD', 'SUB', ('push', 0x100, 2), 'MSTORE',
+           0x60, 'CALLDATALOAD', 'ISZERO', ('ref', 'done'), 'JUMPI',
+           #call the checker at the recipient to send the received amount back
+           0, 'CALLDATALOAD', 0, 'MSTORE', 'ADDRESS', 0x20, 'MSTORE',
+           ('push', 0x100, 2), 'MLOAD', 0x40, 'MSTORE', 0, 0x60, 'MSTORE',
+           0x40, ('push', 0x120, 2), 0x80, 0, 0, 0x20, 'CALLDATALOAD', 'GAS', 'CALL',
+           'ISZERO', 0x40, 'RETURNDATASIZE', 'LT', 'OR', ('ref', 'bad'), 'JUMPI',
+           ('label', 'done'), 0x40, ('push', 0x100, 2), 'RETURN',
+           ('label', 'bad'), 0, 0, 'RETURN']
+    )
+
+
+if __name__ == '__main__':
+    print(transfer_checker().hex())
@@ -1,14 +1,23 @@
+use crate::abi;
 use crate::error::PairSyncError;
 
-use super::dex::Dex;
-use super::pool::Pool;
+use super::dex::{Dex, FeeModel, PoolDiscovery};
+use super::pool::{Pool, PoolVariant};
 use super::throttle::RequestThrottle;
 use ethers::{
+    abi::Token,
+    prelude::Multicall,
     providers::{JsonRpcClient, Middleware, Provider, ProviderError},
-    types::{BlockNumber, Filter, ValueOrArray, H160, U64},
+    types::{BlockNumber, Filter, ValueOrArray, H160, U256, U64},
 };
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use std::sync::{Arc, Mutex};
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+//Multicall3, deployed at the same address on most chains, used to batch the calls made when enumerating factory pairs
+const MULTICALL_ADDRESS: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
 
 //Get all pairs and sync reserve values for each Dex in the `dexes` vec.
 pub async fn sync_pairs<P: 'static + JsonRpcClient>(
@@ -47,7 +56,7 @@ pub async fn sync_pairs_with_throttle<P: 'static + JsonRpcClient>(
             );
 
             let pools = get_all_pools(
-                dex,
+                dex.clone(),
                 async_provider.clone(),
                 BlockNumber::Number(current_block),
                 request_throttle.clone(),
@@ -62,14 +71,9 @@ pub async fn sync_pairs_with_throttle<P: 'static + JsonRpcClient>(
                     .progress_chars("##-"),
             );
 
-            let pools = get_pool_reserves(
-                pools,
-                dex.factory_address,
-                async_provider,
-                request_throttle,
-                progress_bar,
-            )
-            .await?;
+            let pools =
+                get_pool_reserves(pools, dex, async_provider, request_throttle, progress_bar)
+                    .await?;
 
             Ok::<_, PairSyncError<P>>(pools)
         }));
@@ -97,6 +101,18 @@ async fn get_all_pools<P: 'static + JsonRpcClient>(
     request_throttle: Arc<Mutex<RequestThrottle>>,
     progress_bar: ProgressBar,
 ) -> Result<Vec<Pool>, PairSyncError<P>> {
+    //Curve factories do not log the address of deployed pools, so Curve pools are enumerated from the registry or factory instead
+    if let PoolVariant::Curve = dex.pool_variant {
+        return get_all_curve_pools(dex, provider, request_throttle, progress_bar).await;
+    }
+
+    //Pairs are enumerated from the factory for providers that do not serve historical logs
+    if let (PoolVariant::UniswapV2, PoolDiscovery::FactoryEnumeration) =
+        (dex.pool_variant, dex.pool_discovery)
+    {
+        return get_all_pools_from_factory(dex, provider, request_throttle, progress_bar).await;
+    }
+
     //Define the step for searching a range of blocks for pair created events
     let step = 100000;
     //Unwrap can be used here because the creation block was verified within `Dex::new()`
@@ -115,6 +131,7 @@ async fn get_all_pools<P: 'static + JsonRpcClient>(
         let request_throttle = request_throttle.clone();
         let provider = provider.clone();
         let progress_bar = progress_bar.clone();
+        let dex = dex.clone();
 
         //Spawn a new task to get pair created events from the block range
         handles.push(tokio::spawn(async move {
@@ -129,8 +146,8 @@ async fn get_all_pools<P: 'static + JsonRpcClient>(
             let logs = provider
                 .get_logs(
                     &Filter::new()
-                        .topic0(ValueOrArray::Value(
-                            dex.pool_variant.pool_created_event_signature(),
+                        .topic0(ValueOrArray::Array(
+                            dex.pool_variant.pool_created_event_signatures(),
                         ))
                         .address(dex.factory_address)
                         .from_block(BlockNumber::Number(U64([from_block])))
@@ -164,10 +181,167 @@ async fn get_all_pools<P: 'static + JsonRpcClient>(
     Ok(aggregated_pairs)
 }
 
+//Function to get all pools listed by a Curve registry or factory
+async fn get_all_curve_pools<P: 'static + JsonRpcClient>(
+    dex: Dex,
+    provider: Arc<Provider<P>>,
+    request_throttle: Arc<Mutex<RequestThrottle>>,
+    progress_bar: ProgressBar,
+) -> Result<Vec<Pool>, PairSyncError<P>> {
+    let curve_registry = abi::ICurveRegistry::new(dex.factory_address, provider);
+
+    request_throttle.lock().unwrap().increment_or_sleep(1);
+    let pool_count = curve_registry.pool_count().call().await?.as_u64();
+
+    //Initialize the progress bar message
+    progress_bar.set_style(
+        ProgressStyle::with_template("{msg} {bar:40.cyan/blue} {pos:>7}/{len:7} Pools")
+            .unwrap()
+            .progress_chars("##-"),
+    );
+    progress_bar.set_length(pool_count);
+    progress_bar.set_message(format!("Getting all pools from: {}", dex.factory_address));
+
+    //Init a new vec to keep track of tasks
+    let mut handles = vec![];
+
+    //For each index in the pool list, get the pool address asynchronously
+    for i in 0..pool_count {
+        let request_throttle = request_throttle.clone();
+        let curve_registry = curve_registry.clone();
+        let progress_bar = progress_bar.clone();
+
+        handles.push(tokio::spawn(async move {
+            request_throttle.lock().unwrap().increment_or_sleep(1);
+            let address = curve_registry.pool_list(U256::from(i)).call().await?;
+
+            progress_bar.inc(1);
+            Ok::<Pool, PairSyncError<P>>(Pool {
+                factory_address: dex.factory_address,
+                ..Pool::new_curve_pool(address)
+            })
+        }));
+    }
+
+    //Wait for each thread to finish and aggregate the pools
+    let mut aggregated_pools: Vec<Pool> = vec![];
+    for handle in handles {
+        match handle.await {
+            Ok(sync_result) => aggregated_pools.push(sync_result?),
+
+            Err(join_error) => return Err(PairSyncError::JoinError(join_error)),
+        }
+    }
+    Ok(aggregated_pools)
+}
+
+//Function to get all UniswapV2 pairs by enumerating `allPairs` on the factory
+async fn get_all_pools_from_factory<P: 'static + JsonRpcClient>(
+    dex: Dex,
+    provider: Arc<Provider<P>>,
+    request_throttle: Arc<Mutex<RequestThrottle>>,
+    progress_bar: ProgressBar,
+) -> Result<Vec<Pool>, PairSyncError<P>> {
+    //Chains without Multicall3 can not batch the enumeration, so fail before any pairs are requested
+    let multicall_address = H160::from_str(MULTICALL_ADDRESS).unwrap();
+    request_throttle.lock().unwrap().increment_or_sleep(1);
+    if provider
+        .get_code(multicall_address, None)
+        .await?
+        .0
+        .is_empty()
+    {
+        return Err(PairSyncError::ContractNotDeployed(multicall_address));
+    }
+
+    let uniswap_v2_factory = abi::IUniswapV2Factory::new(dex.factory_address, provider.clone());
+
+    request_throttle.lock().unwrap().increment_or_sleep(1);
+    let pair_count = uniswap_v2_factory.all_pairs_length().call().await?.as_u64();
+
+    //Define the number of pairs fetched by each task
+    let step = 100;
+
+    //Initialize the progress bar message
+    progress_bar.set_style(
+        ProgressStyle::with_template("{msg} {bar:40.cyan/blue} {pos:>7}/{len:7} Pairs")
+            .unwrap()
+            .progress_chars("##-"),
+    );
+    progress_bar.set_length(pair_count);
+    progress_bar.set_message(format!("Getting all pairs from: {}", dex.factory_address));
+
+    //Init a new vec to keep track of tasks
+    let mut handles = vec![];
+
+    //For each batch of pair indices, get the pairs asynchronously
+    for from_index in (0..pair_count).step_by(step) {
+        let request_throttle = request_throttle.clone();
+        let provider = provider.clone();
+        let progress_bar = progress_bar.clone();
+        let uniswap_v2_factory = uniswap_v2_factory.clone();
+        let dex = dex.clone();
+
+        handles.push(tokio::spawn(async move {
+            let to_index = (from_index + step as u64).min(pair_count);
+
+            //The pair addresses of the batch are read in one multicall, then both tokens of each pair in another
+            let mut multicall = Multicall::new(provider.clone(), Some(multicall_address)).await?;
+            for i in from_index..to_index {
+                multicall.add_call(uniswap_v2_factory.all_pairs(U256::from(i)));
+            }
+
+            request_throttle.lock().unwrap().increment_or_sleep(1);
+            let addresses = multicall
+                .call_raw()
+                .await?
+                .into_iter()
+                .map(|token| token.into_address())
+                .collect::<Option<Vec<H160>>>()
+                .ok_or(PairSyncError::InvalidPool(dex.factory_address))?;
+
+            multicall.clear_calls();
+            for address in addresses.iter() {
+                let uniswap_v2_pair = abi::IUniswapV2Pair::new(*address, provider.clone());
+                multicall.add_call(uniswap_v2_pair.token_0());
+                multicall.add_call(uniswap_v2_pair.token_1());
+            }
+
+            request_throttle.lock().unwrap().increment_or_sleep(1);
+            let tokens = multicall.call_raw().await?;
+
+            let mut pools = vec![];
+            for (address, pair_tokens) in addresses.into_iter().zip(tokens.chunks(2)) {
+                match pair_tokens {
+                    [Token::Address(token_a), Token::Address(token_b)] => {
+                        pools.push(dex.new_uniswap_v2_pool(address, *token_a, *token_b))
+                    }
+                    _ => return Err(PairSyncError::InvalidPool(address)),
+                }
+
+                progress_bar.inc(1);
+            }
+
+            Ok::<Vec<Pool>, PairSyncError<P>>(pools)
+        }));
+    }
+
+    //Wait for each thread to finish and aggregate the pools
+    let mut aggregated_pools: Vec<Pool> = vec![];
+    for handle in handles {
+        match handle.await {
+            Ok(sync_result) => aggregated_pools.extend(sync_result?),
+
+            Err(join_error) => return Err(PairSyncError::JoinError(join_error)),
+        }
+    }
+    Ok(aggregated_pools)
+}
+
 //Function to get reserves for each pair in the `pairs` vec.
 async fn get_pool_reserves<P: 'static + JsonRpcClient>(
     pools: Vec<Pool>,
-    dex_factory_address: H160,
+    dex: Dex,
     provider: Arc<Provider<P>>,
     request_throttle: Arc<Mutex<RequestThrottle>>,
     progress_bar: ProgressBar,
@@ -179,7 +353,7 @@ async fn get_pool_reserves<P: 'static + JsonRpcClient>(
     progress_bar.set_length(pools.len() as u64);
     progress_bar.set_message(format!(
         "Syncing reserves for pairs from: {}",
-        dex_factory_address
+        dex.factory_address
     ));
 
     //For each pair in the pairs vec, get the reserves asyncrhonously
@@ -187,14 +361,66 @@ async fn get_pool_reserves<P: 'static + JsonRpcClient>(
         let request_throttle = request_throttle.clone();
         let provider = provider.clone();
         let progress_bar = progress_bar.clone();
+        let dex = dex.clone();
 
         //Spawn a new thread to get the reserves for the pair
         handles.push(tokio::spawn(async move {
+            //Multi token pools populate tokens, balances and fees from the pool contract
+            match pool.pool_variant {
+                PoolVariant::Curve => {
+                    //Several calls are made for each coin, so the throttle increments by the maximum number of coins
+                    request_throttle.lock().unwrap().increment_or_sleep(8);
+                    pool.update_curve_state(provider.clone()).await?;
+
+                    progress_bar.inc(1);
+                    return Ok::<Pool, PairSyncError<P>>(pool);
+                }
+                PoolVariant::BalancerV2 => {
+                    //One call is made for each token's decimals on top of the vault and pool calls
+                    request_throttle.lock().unwrap().increment_or_sleep(8);
+                    pool.update_balancer_state(provider.clone()).await?;
+
+                    progress_bar.inc(1);
+                    return Ok::<Pool, PairSyncError<P>>(pool);
+                }
+                PoolVariant::UniswapV4 => {
+                    //The pool state is read with two calls to the PoolManager, plus a call for each token's decimals
+                    request_throttle.lock().unwrap().increment_or_sleep(4);
+                    pool.update_uniswap_v4_state(provider.clone()).await?;
+
+                    progress_bar.inc(1);
+                    return Ok::<Pool, PairSyncError<P>>(pool);
+                }
+                PoolVariant::Solidly => {
+                    //Solidly fees are set on the factory rather than the pair
+                    request_throttle.lock().unwrap().increment_or_sleep(1);
+                    pool.fee = dex
+                        .get_solidly_fee(pool.address, pool.stable, provider.clone())
+                        .await?;
+                }
+                PoolVariant::UniswapV2 => {
+                    //Fees that are not fixed are read from the pair or the factory
+                    if !matches!(dex.fee_model, FeeModel::Fixed(_)) {
+                        request_throttle.lock().unwrap().increment_or_sleep(1);
+                        pool.fee = dex
+                            .get_uniswap_v2_fee(pool.address, provider.clone())
+                            .await?;
+                    }
+                }
+                PoolVariant::Algebra => {
+                    //The tick spacing is not part of the creation event, so it is read once along with the state
+                    if pool.tick_spacing == 0 {
+                        request_throttle.lock().unwrap().increment_or_sleep(1);
+                    }
+                }
+                PoolVariant::UniswapV3 => {}
+            }
+
             //Get the pair reserves
-            //If the pair is uniswapv3, two rpc calls are made to initialize reserves
+            //If the pair is uniswapv3 or algebra, two rpc calls are made to initialize reserves
             //Because of this, the throttle increments by two to be conservative
             request_throttle.lock().unwrap().increment_or_sleep(2);
-            (pool.reserve_0, pool.reserve_1) = pool.get_reserves(provider.clone()).await?;
+            pool.update_reserves(provider.clone()).await?;
 
             // Make a call to get token0 to initialize a_to_b
             request_throttle.lock().unwrap().increment_or_sleep(1);
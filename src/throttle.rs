@@ -37,7 +37,7 @@ impl RequestThrottle {
                 .as_secs()
                 < 1
         {
-            if self.requests_per_second == self.requests_per_second_limit {
+            if self.requests_per_second >= self.requests_per_second_limit {
                 sleep(Duration::from_secs(1));
                 self.requests_per_second = 0;
                 self.last_request_timestamp = SystemTime::now();
@@ -0,0 +1,152 @@
+use std::{fs, path::Path, str::FromStr};
+
+use ethers::types::H160;
+use serde::Deserialize;
+
+use crate::error::TokenListError;
+
+//A token list following the Token Lists JSON schema (https://tokenlists.org), ie. the Uniswap default list.
+//Only the fields needed to select tokens are read, other fields are ignored.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenList {
+    #[serde(default)]
+    pub name: String,
+    pub tokens: Vec<TokenInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenInfo {
+    pub chain_id: u64,
+    pub address: String,
+    pub symbol: Option<String>,
+    pub name: Option<String>,
+    pub decimals: Option<u8>,
+}
+
+impl TokenList {
+    pub fn from_json(token_list: &str) -> Result<TokenList, TokenListError> {
+        Ok(serde_json::from_str(token_list)?)
+    }
+
+    pub fn from_file<T: AsRef<Path>>(path: T) -> Result<TokenList, TokenListError> {
+        TokenList::from_json(&fs::read_to_string(path)?)
+    }
+
+    //Gets the addresses of the tokens on the chain, to be used as an allowlist or blacklist
+    pub fn get_addresses(&self, chain_id: u64) -> Result<Vec<H160>, TokenListError> {
+        self.tokens
+            .iter()
+            .filter(|token| token.chain_id == chain_id)
+            .map(|token| parse_address(&token.address))
+            .collect()
+    }
+}
+
+//Parses a plain address list, either a JSON array of addresses or text with addresses separated by
+//newlines, commas or whitespace. Text after a `#` on a line is ignored.
+pub fn parse_address_list(address_list: &str) -> Result<Vec<H160>, TokenListError> {
+    if address_list.trim_start().starts_with('[') {
+        let addresses: Vec<String> = serde_json::from_str(address_list)?;
+        return addresses
+            .iter()
+            .map(|address| parse_address(address))
+            .collect();
+    }
+
+    address_list
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(|line| line.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|address| !address.is_empty())
+        .map(parse_address)
+        .collect()
+}
+
+//Reads a plain address list from a file, see `parse_address_list`
+pub fn read_address_list<T: AsRef<Path>>(path: T) -> Result<Vec<H160>, TokenListError> {
+    parse_address_list(&fs::read_to_string(path)?)
+}
+
+fn parse_address(address: &str) -> Result<H160, TokenListError> {
+    H160::from_str(address.trim()).map_err(|_| TokenListError::InvalidAddress(address.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WETH: &str = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+    const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+
+    #[test]
+    fn test_get_addresses() {
+        let token_list = TokenList::from_json(&format!(
+            r#"{{
+                "name": "Default",
+                "timestamp": "2024-01-01T00:00:00.000Z",
+                "tokens": [
+                    {{ "chainId": 1, "address": "{}", "symbol": "WETH", "decimals": 18 }},
+                    {{ "chainId": 10, "address": "0x4200000000000000000000000000000000000006" }},
+                    {{ "chainId": 1, "address": "{}", "symbol": "USDC", "name": "USD Coin", "decimals": 6 }}
+                ]
+            }}"#,
+            WETH, USDC
+        ))
+        .unwrap();
+
+        assert_eq!(token_list.name, "Default");
+        assert_eq!(
+            token_list.get_addresses(1).unwrap(),
+            vec![H160::from_str(WETH).unwrap(), H160::from_str(USDC).unwrap()]
+        );
+        assert_eq!(token_list.get_addresses(10).unwrap().len(), 1);
+        assert!(token_list.get_addresses(56).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_addresses_invalid_address() {
+        let token_list =
+            TokenList::from_json(r#"{ "tokens": [{ "chainId": 1, "address": "0xC02a" }] }"#)
+                .unwrap();
+
+        assert!(matches!(
+            token_list.get_addresses(1),
+            Err(TokenListError::InvalidAddress(address)) if address == "0xC02a"
+        ));
+    }
+
+    #[test]
+    fn test_parse_address_list() {
+        let addresses = vec![H160::from_str(WETH).unwrap(), H160::from_str(USDC).unwrap()];
+
+        assert_eq!(
+            parse_address_list(&format!(r#"["{}", "{}"]"#, WETH, USDC)).unwrap(),
+            addresses
+        );
+        assert_eq!(
+            parse_address_list(&format!("# Base tokens\n{} # WETH\n\n  {},\n", WETH, USDC))
+                .unwrap(),
+            addresses
+        );
+        assert_eq!(
+            parse_address_list(&format!("{},{} {}", WETH, USDC, WETH))
+                .unwrap()
+                .len(),
+            3
+        );
+        assert!(parse_address_list("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_address_list_invalid_address() {
+        assert!(matches!(
+            parse_address_list(&format!("{}\nnot_an_address", WETH)),
+            Err(TokenListError::InvalidAddress(address)) if address == "not_an_address"
+        ));
+        assert!(matches!(
+            parse_address_list("[1, 2]"),
+            Err(TokenListError::JsonError(_))
+        ));
+    }
+}
@@ -0,0 +1,469 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ethers::{
+    providers::{JsonRpcClient, Middleware, Provider},
+    types::H160,
+};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+use crate::{
+    abi,
+    chainlink::FeedRegistry,
+    dex::Dex,
+    error::PairSyncError,
+    filter::get_token_price,
+    pipeline::FilterContext,
+    pool::{is_native_token, Pool, PoolKey, PoolVariant},
+    usd::UsdReference,
+};
+
+//Token pools are valued in. Tokens are priced against the base token with the dexes, ie. weth, wbnb or usdc,
+//and the base token price is converted through the reference pools in order, ie. a weth/usdc pool to value in USD.
+#[derive(Debug, Clone)]
+pub struct QuoteToken {
+    pub base_token: H160,
+    pub reference_pools: Vec<Pool>,
+    //Converts the token reached after the reference pools to USD with several stablecoin pools
+    pub usd_reference: Option<UsdReference>,
+    //Chainlink feeds reporting token prices in the base token, used before pricing with the dexes
+    pub feed_registry: Option<FeedRegistry>,
+}
+
+impl QuoteToken {
+    //Values pools in the base token
+    pub fn new(base_token: H160) -> QuoteToken {
+        QuoteToken {
+            base_token,
+            reference_pools: vec![],
+            usd_reference: None,
+            feed_registry: None,
+        }
+    }
+
+    //Adds a pool pairing the current quote token with the next one
+    pub fn with_reference_pool(mut self, reference_pool: Pool) -> QuoteToken {
+        self.reference_pools.push(reference_pool);
+        self
+    }
+
+    //Values pools in USD, converting the token reached after the reference pools with the usd reference
+    pub fn with_usd_reference(mut self, usd_reference: UsdReference) -> QuoteToken {
+        self.usd_reference = Some(usd_reference);
+        self
+    }
+
+    //Prices tokens with Chainlink feeds where available, falling back to the dexes when a feed is missing or stale.
+    //Feeds must report prices in the base token, ie. X / ETH feeds for a weth base token.
+    pub fn with_feed_registry(mut self, feed_registry: FeedRegistry) -> QuoteToken {
+        self.feed_registry = Some(feed_registry);
+        self
+    }
+
+    //Gets the token values are expressed in, after following the reference pools from the base token.
+    //Returns None if values are converted to USD with a usd reference.
+    pub fn quote_token(&self) -> Option<H160> {
+        if self.usd_reference.is_some() {
+            return None;
+        }
+
+        let mut quote_token = self.base_token;
+
+        for reference_pool in self.reference_pools.iter() {
+            if reference_pool.token_a == quote_token {
+                quote_token = reference_pool.token_b;
+            } else if reference_pool.token_b == quote_token {
+                quote_token = reference_pool.token_a;
+            }
+        }
+
+        Some(quote_token)
+    }
+
+    //Number of calls to the node made by `get_base_token_price`
+    pub(crate) fn request_count(&self) -> usize {
+        self.reference_pools.len()
+            + self
+                .usd_reference
+                .as_ref()
+                .map_or(0, |usd_reference| usd_reference.request_count())
+    }
+
+    //Gets the price of one base token in the quote token from the current state of the reference pools.
+    //With a usd reference, the price fails with the disagreeing sources if any stablecoin pool or the Chainlink
+    //aggregator deviates from the aggregated price by more than the tolerance.
+    pub async fn get_base_token_price<P: JsonRpcClient>(
+        &self,
+        provider: Arc<Provider<P>>,
+    ) -> Result<f64, PairSyncError<P>> {
+        let mut token = self.base_token;
+        let mut price = 1.0;
+
+        for reference_pool in self.reference_pools.iter() {
+            //`get_price(a_per_b)` prices token_b in units of token_a, so the token is priced in the other token with the inverse
+            let token_is_a = if reference_pool.token_a == token {
+                true
+            } else if reference_pool.token_b == token {
+                false
+            } else {
+                return Err(PairSyncError::InvalidReferencePool(
+                    reference_pool.address,
+                    token,
+                ));
+            };
+
+            price *= reference_pool
+                .get_price(!token_is_a, provider.clone())
+                .await?;
+
+            token = if token_is_a {
+                reference_pool.token_b
+            } else {
+                reference_pool.token_a
+            };
+        }
+
+        if let Some(usd_reference) = &self.usd_reference {
+            if usd_reference.token != token {
+                return Err(PairSyncError::InvalidReferencePool(
+                    usd_reference.token,
+                    token,
+                ));
+            }
+
+            let usd_price = usd_reference.get_usd_price(provider).await?;
+            if !usd_price.is_consistent() {
+                return Err(PairSyncError::PriceSourcesDisagree(
+                    token,
+                    usd_price.disagreements,
+                ));
+            }
+
+            price *= usd_price.price;
+        }
+
+        Ok(price)
+    }
+}
+
+//Value of one of the pool's tokens in the quote token
+#[derive(Debug, Clone)]
+pub struct TokenValue {
+    pub token: H160,
+    //Balance of the token held by the pool, adjusted for decimals.
+    //Concentrated liquidity pools are valued with their token balances rather than their virtual reserves.
+    pub balance: f64,
+    //Price of one token in the quote token, None if the token has no route to the base token in the dexes
+    pub price: Option<f64>,
+    //Value of the balance in the quote token, None if the token could not be priced
+    pub value: Option<f64>,
+}
+
+//Total value locked in a pool, in the quote token
+#[derive(Debug, Clone)]
+pub struct PoolTvl {
+    pub pool: Pool,
+    //Sum of the values of the tokens that could be priced
+    pub tvl: f64,
+    pub token_values: Vec<TokenValue>,
+}
+
+impl PoolTvl {
+    pub fn new(pool: Pool, token_values: Vec<TokenValue>) -> PoolTvl {
+        PoolTvl {
+            tvl: token_values
+                .iter()
+                .filter_map(|token_value| token_value.value)
+                .sum(),
+            pool,
+            token_values,
+        }
+    }
+
+    //Returns true if every token in the pool could be priced
+    pub fn is_complete(&self) -> bool {
+        self.token_values
+            .iter()
+            .all(|token_value| token_value.value.is_some())
+    }
+}
+
+//Gets the total value locked in each pool in the quote token, ie. `QuoteToken::new(weth_address)` to value in weth.
+//Tokens are priced against the base token with the best pool found in the dexes.
+pub async fn get_pool_tvls<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    dexes: Vec<Dex>,
+    quote_token: QuoteToken,
+    provider: Arc<Provider<P>>,
+) -> Result<Vec<PoolTvl>, PairSyncError<P>> {
+    get_pool_tvls_with_throttle(pools, dexes, quote_token, provider, 0).await
+}
+
+//Gets the total value locked in each pool in the quote token, see `get_pool_tvls`
+pub async fn get_pool_tvls_with_throttle<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    dexes: Vec<Dex>,
+    quote_token: QuoteToken,
+    provider: Arc<Provider<P>>,
+    requests_per_second_limit: usize,
+) -> Result<Vec<PoolTvl>, PairSyncError<P>> {
+    get_pool_tvls_with_context(
+        pools,
+        dexes,
+        &quote_token,
+        &FilterContext::new(provider, requests_per_second_limit),
+    )
+    .await
+}
+
+//Gets the total value locked in each pool in the quote token, reusing the token prices cached on the context.
+//UniswapV4 pools are skipped, as the PoolManager holds the tokens of every pool together.
+pub async fn get_pool_tvls_with_context<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    dexes: Vec<Dex>,
+    quote_token: &QuoteToken,
+    context: &FilterContext<P>,
+) -> Result<Vec<PoolTvl>, PairSyncError<P>> {
+    let mut pool_tvls = vec![];
+
+    for (pool, token_values) in get_token_values(pools, dexes, quote_token, context).await? {
+        match token_values {
+            Ok(token_values) => pool_tvls.push(PoolTvl::new(pool, token_values)),
+            Err(PairSyncError::UnknownPoolBalances(_)) => {}
+            Err(pair_sync_error) => return Err(pair_sync_error),
+        }
+    }
+
+    Ok(pool_tvls)
+}
+
+//Gets the balance of each token held by each pool, keyed by pool, ie. to build a `PriceOracle` that weights
+//concentrated liquidity pools by the tokens they hold. UniswapV4 pools are skipped.
+pub async fn get_pool_held_balances<P: 'static + JsonRpcClient>(
+    pools: &[Pool],
+    provider: Arc<Provider<P>>,
+) -> Result<HashMap<PoolKey, Vec<(H160, u8, u128)>>, PairSyncError<P>> {
+    get_pool_held_balances_with_throttle(pools, provider, 0).await
+}
+
+//Gets the balance of each token held by each pool, see `get_pool_held_balances`
+pub async fn get_pool_held_balances_with_throttle<P: 'static + JsonRpcClient>(
+    pools: &[Pool],
+    provider: Arc<Provider<P>>,
+    requests_per_second_limit: usize,
+) -> Result<HashMap<PoolKey, Vec<(H160, u8, u128)>>, PairSyncError<P>> {
+    let context = FilterContext::new(provider, requests_per_second_limit);
+    let mut pool_held_balances = HashMap::new();
+
+    for pool in pools {
+        match get_held_balances(pool, &context).await {
+            Ok(held_balances) => {
+                pool_held_balances.insert(pool.get_pool_key(), held_balances);
+            }
+            Err(PairSyncError::UnknownPoolBalances(_)) => {}
+            Err(pair_sync_error) => return Err(pair_sync_error),
+        }
+    }
+
+    Ok(pool_held_balances)
+}
+
+//Gets the value of each token in each pool, returning each pool alongside its result so errors can be attributed to the pool
+pub(crate) async fn get_token_values<P: 'static + JsonRpcClient>(
+    pools: Vec<Pool>,
+    dexes: Vec<Dex>,
+    quote_token: &QuoteToken,
+    context: &FilterContext<P>,
+) -> Result<Vec<(Pool, Result<Vec<TokenValue>, PairSyncError<P>>)>, PairSyncError<P>> {
+    let multi_progress_bar = MultiProgress::new();
+    let progress_bar = multi_progress_bar.add(ProgressBar::new(0));
+    progress_bar.set_style(
+        ProgressStyle::with_template("{msg} {bar:40.cyan/blue} {pos:>7}/{len:7} Pools Valued")
+            .unwrap()
+            .progress_chars("##-"),
+    );
+
+    progress_bar.set_length(pools.len() as u64);
+    progress_bar.set_message("Valuing pools: ");
+
+    //Price of one base token in the quote token
+    context
+        .request_throttle
+        .lock()
+        .unwrap()
+        .increment_or_sleep(quote_token.request_count());
+    let base_token_price = quote_token
+        .get_base_token_price(context.provider.clone())
+        .await?;
+    let base_token = quote_token.base_token;
+    let feed_registry = Arc::new(quote_token.feed_registry.clone());
+
+    let mut handles = vec![];
+    for pool in pools {
+        let context = context.clone();
+        let dexes = dexes.clone();
+        let feed_registry = feed_registry.clone();
+        let progress_bar = progress_bar.clone();
+
+        handles.push(tokio::spawn(async move {
+            let token_values = async {
+                let mut token_values = vec![];
+
+                for (token, decimals, balance) in get_held_balances(&pool, &context).await? {
+                    let price = get_cached_token_price(
+                        token,
+                        base_token,
+                        &dexes,
+                        feed_registry.as_ref().as_ref(),
+                        &context,
+                    )
+                    .await?
+                    .map(|token_price| token_price * base_token_price);
+
+                    let balance = balance as f64 / 10f64.powf(decimals.into());
+
+                    token_values.push(TokenValue {
+                        token,
+                        balance,
+                        price,
+                        value: price.map(|price| price * balance),
+                    });
+                }
+
+                Ok::<_, PairSyncError<P>>(token_values)
+            }
+            .await;
+
+            progress_bar.inc(1);
+            (pool, token_values)
+        }));
+    }
+
+    let mut pool_token_values = vec![];
+    for handle in handles {
+        match handle.await {
+            Ok(token_values) => pool_token_values.push(token_values),
+            Err(join_error) => return Err(PairSyncError::JoinError(join_error)),
+        }
+    }
+
+    Ok(pool_token_values)
+}
+
+//Gets the balance of each token held by the pool, as (token, decimals, balance).
+//The reserves of concentrated liquidity pools are virtual reserves, which can be far larger than the tokens
+//the pool holds, so their balances are read from the tokens instead.
+async fn get_held_balances<P: 'static + JsonRpcClient>(
+    pool: &Pool,
+    context: &FilterContext<P>,
+) -> Result<Vec<(H160, u8, u128)>, PairSyncError<P>> {
+    match pool.pool_variant {
+        PoolVariant::UniswapV3 | PoolVariant::Algebra => {
+            let mut held_balances = vec![];
+
+            for (token, decimals, _) in pool.get_token_balances() {
+                context
+                    .request_throttle
+                    .lock()
+                    .unwrap()
+                    .increment_or_sleep(1);
+
+                let balance = if is_native_token(token) {
+                    context.provider.get_balance(pool.address, None).await?
+                } else {
+                    abi::IErc20::new(token, context.provider.clone())
+                        .balance_of(pool.address)
+                        .call()
+                        .await?
+                };
+
+                //Balances above u128::MAX, ie. of tokens with an unbounded supply, saturate instead of truncating
+                held_balances.push((
+                    token,
+                    decimals,
+                    u128::try_from(balance).unwrap_or(u128::MAX),
+                ));
+            }
+
+            Ok(held_balances)
+        }
+
+        //The PoolManager does not account for the tokens of each pool separately
+        PoolVariant::UniswapV4 => Err(PairSyncError::UnknownPoolBalances(pool.address)),
+
+        PoolVariant::UniswapV2
+        | PoolVariant::Solidly
+        | PoolVariant::Curve
+        | PoolVariant::BalancerV2 => Ok(pool.get_token_balances()),
+    }
+}
+
+//Gets the price of one token in the base token, using the prices cached on the context.
+//The token's Chainlink feed is used if it has a fresh answer, otherwise the token is priced with the dexes.
+//Returns None if the token is not paired with the base token in any of the dexes.
+async fn get_cached_token_price<P: 'static + JsonRpcClient>(
+    token: H160,
+    base_token: H160,
+    dexes: &Vec<Dex>,
+    feed_registry: Option<&FeedRegistry>,
+    context: &FilterContext<P>,
+) -> Result<Option<f64>, PairSyncError<P>> {
+    let cached_price = context
+        .token_prices
+        .lock()
+        .unwrap()
+        .get(&(token, base_token))
+        .copied();
+
+    if let Some(price) = cached_price {
+        return Ok(Some(price));
+    }
+
+    if let Some(feed_registry) = feed_registry.filter(|registry| registry.get_feed(token).is_some())
+    {
+        //Reading a feed makes two calls, for the latest round and the latest block
+        context
+            .request_throttle
+            .lock()
+            .unwrap()
+            .increment_or_sleep(2);
+
+        if let Some(price) = feed_registry
+            .get_price(token, context.provider.clone())
+            .await?
+        {
+            context
+                .token_prices
+                .lock()
+                .unwrap()
+                .insert((token, base_token), price);
+            return Ok(Some(price));
+        }
+    }
+
+    context
+        .request_throttle
+        .lock()
+        .unwrap()
+        .increment_or_sleep(1);
+
+    match get_token_price(
+        token,
+        base_token,
+        dexes,
+        context.provider.clone(),
+        context.request_throttle.clone(),
+    )
+    .await
+    {
+        Ok(price) => {
+            context
+                .token_prices
+                .lock()
+                .unwrap()
+                .insert((token, base_token), price);
+            Ok(Some(price))
+        }
+        Err(PairSyncError::PairDoesNotExistInDexes(_, _)) => Ok(None),
+        Err(pair_sync_error) => Err(pair_sync_error),
+    }
+}
@@ -0,0 +1,328 @@
+use std::{collections::HashSet, sync::Arc};
+
+use ethers::{
+    providers::{JsonRpcClient, Provider},
+    types::H160,
+};
+
+use crate::{
+    chainlink::get_chainlink_price,
+    error::PairSyncError,
+    pool::{Pool, PoolKey},
+};
+
+//Relative deviation from the aggregated price allowed before a source is reported, ie. 0.02 for 2%
+pub const DEFAULT_USD_PRICE_TOLERANCE: f64 = 0.02;
+
+//How the prices of the stablecoin pools are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceAggregation {
+    //Median of the pool prices, which ignores a minority of manipulated or depegged pools
+    Median,
+    //Mean of the pool prices, weighted by the stablecoin balance of each pool
+    LiquidityWeighted,
+}
+
+//USD price of a token, ie. weth, aggregated from several pools pairing the token with USD stablecoins and optionally
+//cross-checked against a Chainlink aggregator. The token is priced against every other token in each pool,
+//ie. each stablecoin of a multi coin Curve pool, and stablecoins are assumed to be worth one dollar.
+#[derive(Debug, Clone)]
+pub struct UsdReference {
+    pub token: H160,
+    pub stable_pools: Vec<Pool>,
+    //Tokens priced as stablecoins. If empty, every other token in the pools is assumed to be a stablecoin.
+    pub stablecoins: HashSet<H160>,
+    pub aggregation: PriceAggregation,
+    //Chainlink aggregator reporting the USD price of the token, ie. the ETH / USD feed
+    pub chainlink_aggregator: Option<H160>,
+    //Maximum number of seconds between updates of the Chainlink aggregator, older answers are rejected
+    pub chainlink_heartbeat: u64,
+    pub tolerance: f64,
+}
+
+//Price of the token against a single stablecoin in a pool
+#[derive(Debug, Clone, Copy)]
+pub struct PoolPrice {
+    pub pool: PoolKey,
+    pub stablecoin: H160,
+    pub price: f64,
+    //Balance of the stablecoin held by the pool, adjusted for decimals
+    pub liquidity: f64,
+}
+
+//Source of a price compared against the aggregated price
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceSource {
+    //Pool and the stablecoin the token was priced against
+    Pool(PoolKey, H160),
+    //Address of the Chainlink aggregator
+    Chainlink(H160),
+}
+
+//A price source that deviates from the aggregated price by more than the tolerance
+#[derive(Debug, Clone, Copy)]
+pub struct PriceDisagreement {
+    pub source: PriceSource,
+    pub price: f64,
+    //Relative deviation from the aggregated price
+    pub deviation: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct UsdPrice {
+    //Aggregated price of the stablecoin pools
+    pub price: f64,
+    pub pool_prices: Vec<PoolPrice>,
+    pub chainlink_price: Option<f64>,
+    pub disagreements: Vec<PriceDisagreement>,
+}
+
+impl UsdPrice {
+    //Returns true if every source is within the tolerance of the aggregated price
+    pub fn is_consistent(&self) -> bool {
+        self.disagreements.is_empty()
+    }
+
+    //Returns true if the Chainlink price is within the tolerance of the aggregated price, or there is no Chainlink price
+    pub fn chainlink_agrees(&self, chainlink_aggregator: Option<H160>) -> bool {
+        match chainlink_aggregator {
+            Some(chainlink_aggregator) => !self.disagreements.iter().any(|disagreement| {
+                disagreement.source == PriceSource::Chainlink(chainlink_aggregator)
+            }),
+            None => true,
+        }
+    }
+}
+
+impl UsdReference {
+    pub fn new(token: H160, stable_pools: Vec<Pool>) -> UsdReference {
+        UsdReference {
+            token,
+            stable_pools,
+            stablecoins: HashSet::new(),
+            aggregation: PriceAggregation::Median,
+            chainlink_aggregator: None,
+            chainlink_heartbeat: 0,
+            tolerance: DEFAULT_USD_PRICE_TOLERANCE,
+        }
+    }
+
+    //Only prices the token against these stablecoins, ie. to skip wbtc in a usdt/wbtc/weth pool
+    pub fn with_stablecoins(mut self, stablecoins: Vec<H160>) -> UsdReference {
+        self.stablecoins = stablecoins.into_iter().collect();
+        self
+    }
+
+    pub fn with_aggregation(mut self, aggregation: PriceAggregation) -> UsdReference {
+        self.aggregation = aggregation;
+        self
+    }
+
+    //Cross-checks the price against a Chainlink aggregator, ie. the ETH / USD feed with a heartbeat of 3600 seconds
+    pub fn with_chainlink_aggregator(
+        mut self,
+        chainlink_aggregator: H160,
+        heartbeat: u64,
+    ) -> UsdReference {
+        self.chainlink_aggregator = Some(chainlink_aggregator);
+        self.chainlink_heartbeat = heartbeat;
+        self
+    }
+
+    pub fn with_tolerance(mut self, tolerance: f64) -> UsdReference {
+        self.tolerance = tolerance;
+        self
+    }
+
+    //Number of calls to the node made by `get_usd_price`
+    pub(crate) fn request_count(&self) -> usize {
+        self.stable_pools.len() + self.chainlink_aggregator.map_or(0, |_| 3)
+    }
+
+    //Gets the USD price of the token from the current state of the stablecoin pools.
+    //Pools with no liquidity are skipped, and the sources that disagree with the aggregated price are reported.
+    pub async fn get_usd_price<P: JsonRpcClient>(
+        &self,
+        provider: Arc<Provider<P>>,
+    ) -> Result<UsdPrice, PairSyncError<P>> {
+        let mut pool_prices = vec![];
+
+        for stable_pool in self.stable_pools.iter() {
+            let mut stable_pool = stable_pool.clone();
+            stable_pool.update_reserves(provider.clone()).await?;
+
+            //The token balances include every coin of multi token pools, which are only known once the pool is updated
+            let token_balances = stable_pool.get_token_balances();
+            if !token_balances
+                .iter()
+                .any(|(token, _, _)| *token == self.token)
+            {
+                return Err(PairSyncError::InvalidReferencePool(
+                    stable_pool.address,
+                    self.token,
+                ));
+            }
+
+            for (stablecoin, decimals, balance) in token_balances {
+                if stablecoin == self.token
+                    || !(self.stablecoins.is_empty() || self.stablecoins.contains(&stablecoin))
+                {
+                    continue;
+                }
+
+                let liquidity = balance as f64 / 10f64.powf(decimals.into());
+
+                match stable_pool.get_spot_price(self.token, stablecoin) {
+                    Ok(price) if price.is_finite() && price > 0.0 && liquidity > 0.0 => pool_prices
+                        .push(PoolPrice {
+                            pool: stable_pool.get_pool_key(),
+                            stablecoin,
+                            price,
+                            liquidity,
+                        }),
+                    _ => {}
+                }
+            }
+        }
+
+        let price = aggregate_prices(&pool_prices, self.aggregation)
+            .ok_or(PairSyncError::NoPriceSources(self.token))?;
+
+        let mut disagreements: Vec<PriceDisagreement> = pool_prices
+            .iter()
+            .map(|pool_price| PriceDisagreement {
+                source: PriceSource::Pool(pool_price.pool, pool_price.stablecoin),
+                price: pool_price.price,
+                deviation: (pool_price.price - price).abs() / price,
+            })
+            .filter(|disagreement| disagreement.deviation > self.tolerance)
+            .collect();
+
+        let chainlink_price = match self.chainlink_aggregator {
+            Some(chainlink_aggregator) => {
+                let chainlink_price =
+                    get_chainlink_price(chainlink_aggregator, self.chainlink_heartbeat, provider)
+                        .await?;
+                let deviation = (chainlink_price - price).abs() / price;
+
+                if deviation > self.tolerance {
+                    disagreements.push(PriceDisagreement {
+                        source: PriceSource::Chainlink(chainlink_aggregator),
+                        price: chainlink_price,
+                        deviation,
+                    });
+                }
+
+                Some(chainlink_price)
+            }
+            None => None,
+        };
+
+        Ok(UsdPrice {
+            price,
+            pool_prices,
+            chainlink_price,
+            disagreements,
+        })
+    }
+}
+
+fn aggregate_prices(pool_prices: &[PoolPrice], aggregation: PriceAggregation) -> Option<f64> {
+    if pool_prices.is_empty() {
+        return None;
+    }
+
+    match aggregation {
+        PriceAggregation::Median => {
+            let mut prices: Vec<f64> = pool_prices
+                .iter()
+                .map(|pool_price| pool_price.price)
+                .collect();
+            prices.sort_by(|a, b| a.total_cmp(b));
+
+            let middle = prices.len() / 2;
+            if prices.len() % 2 == 1 {
+                Some(prices[middle])
+            } else {
+                Some((prices[middle - 1] + prices[middle]) / 2.0)
+            }
+        }
+
+        PriceAggregation::LiquidityWeighted => {
+            let liquidity: f64 = pool_prices
+                .iter()
+                .map(|pool_price| pool_price.liquidity)
+                .sum();
+
+            Some(
+                pool_prices
+                    .iter()
+                    .map(|pool_price| pool_price.price * pool_price.liquidity)
+                    .sum::<f64>()
+                    / liquidity,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool_price(price: f64, liquidity: f64) -> PoolPrice {
+        PoolPrice {
+            pool: PoolKey::Address(H160::zero()),
+            stablecoin: H160::zero(),
+            price,
+            liquidity,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_prices_median() {
+        //A single manipulated pool does not move the median
+        let pool_prices = vec![
+            pool_price(2001.0, 1.0),
+            pool_price(1999.0, 1.0),
+            pool_price(3000.0, 1.0),
+        ];
+        assert_eq!(
+            aggregate_prices(&pool_prices, PriceAggregation::Median),
+            Some(2001.0)
+        );
+
+        //An even number of prices uses the mean of the middle two
+        let pool_prices = vec![
+            pool_price(2000.0, 1.0),
+            pool_price(1990.0, 1.0),
+            pool_price(2010.0, 1.0),
+            pool_price(2004.0, 1.0),
+        ];
+        assert_eq!(
+            aggregate_prices(&pool_prices, PriceAggregation::Median),
+            Some(2002.0)
+        );
+    }
+
+    #[test]
+    fn test_aggregate_prices_liquidity_weighted() {
+        let pool_prices = vec![
+            pool_price(2000.0, 3_000_000.0),
+            pool_price(2100.0, 1_000_000.0),
+        ];
+
+        assert_eq!(
+            aggregate_prices(&pool_prices, PriceAggregation::LiquidityWeighted),
+            Some(2025.0)
+        );
+    }
+
+    #[test]
+    fn test_aggregate_prices_empty() {
+        assert_eq!(aggregate_prices(&[], PriceAggregation::Median), None);
+        assert_eq!(
+            aggregate_prices(&[], PriceAggregation::LiquidityWeighted),
+            None
+        );
+    }
+}